@@ -0,0 +1,239 @@
+// Counts the syscalls `els` makes per listed entry and times a few listings.
+//
+//   cargo bench --bench syscalls
+//   ELS_BENCH_BIN=/path/to/other/els cargo bench --bench syscalls   # compare another build
+//
+// The fixed cost of starting up is measured on an empty directory and subtracted,
+// so the table shows what each additional entry costs. The default and `--full` listings
+// pass no other flags, so builds that predate the newer options can be compared on them;
+// listings a build rejects are reported as unsupported.
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::os::unix::fs::symlink;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use tempfile::TempDir;
+
+const ENTRIES: usize = 2000;
+const TIMING_RUNS: usize = 7;
+
+const LISTINGS: [(&str, &[&str]); 4] = [
+    ("default", &[]),
+    ("full", &["--full"]),
+    ("names", &["--columns", "name"]),
+    ("flat", &["--columns", "name", "--no-dirs-first"]),
+];
+
+fn get_bin() -> String {
+    std::env::var("ELS_BENCH_BIN").unwrap_or_else(|_| env!("CARGO_BIN_EXE_els").to_string())
+}
+
+// A mix of what real directories hold: text and empty files, subdirectories and
+// symlinks to each, including dangling ones
+fn make_fixture(root: &Path, entries: usize) {
+    for i in 0..entries {
+        let path = root.join(format!("entry{:05}", i));
+        match i % 6 {
+            0 => {
+                let mut file = File::create(&path).unwrap();
+                writeln!(file, "line {}", i).unwrap();
+            }
+            1 => {
+                File::create(&path).unwrap();
+            }
+            2 => {
+                fs::create_dir(&path).unwrap();
+                File::create(path.join("inner.txt")).unwrap();
+            }
+            3 => symlink(format!("entry{:05}", i - 3), &path).unwrap(),
+            4 => symlink(format!("entry{:05}", i - 2), &path).unwrap(),
+            _ => symlink("missing", &path).unwrap(),
+        }
+    }
+}
+
+// Colors are kept off through the environment, which older builds simply ignore
+fn make_command(dir: &Path, flags: &[&str]) -> Command {
+    let mut cmd = Command::new(get_bin());
+    cmd.args(flags)
+        .arg(dir)
+        .env("NO_COLOR", "1")
+        .env_remove("CLICOLOR_FORCE")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    cmd
+}
+
+fn is_supported(dir: &Path, flags: &[&str]) -> bool {
+    make_command(dir, flags).status().is_ok_and(|s| s.success())
+}
+
+fn time_listing(dir: &Path, flags: &[&str]) -> Duration {
+    let mut runs: Vec<Duration> = (0..TIMING_RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            let status = make_command(dir, flags).status().unwrap();
+            assert!(status.success());
+            start.elapsed()
+        })
+        .collect();
+    runs.sort();
+    runs[TIMING_RUNS / 2]
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu", target_arch = "x86_64"))]
+mod trace {
+    use std::collections::HashMap;
+    use std::os::unix::process::CommandExt;
+    use std::process::Command;
+
+    pub const GROUPS: [&str; 6] = ["stat", "access", "readlink", "open", "getdents", "total"];
+
+    fn get_group(nr: i64) -> Option<usize> {
+        match nr {
+            libc::SYS_statx
+            | libc::SYS_newfstatat
+            | libc::SYS_stat
+            | libc::SYS_lstat
+            | libc::SYS_fstat => Some(0),
+            libc::SYS_access | libc::SYS_faccessat | libc::SYS_faccessat2 => Some(1),
+            libc::SYS_readlink | libc::SYS_readlinkat => Some(2),
+            libc::SYS_open | libc::SYS_openat => Some(3),
+            libc::SYS_getdents64 => Some(4),
+            _ => None,
+        }
+    }
+
+    // Runs the command under ptrace, following every thread the row workers spawn
+    pub fn count_syscalls(mut cmd: Command) -> Option<[u64; 6]> {
+        unsafe {
+            cmd.pre_exec(|| {
+                if libc::ptrace(libc::PTRACE_TRACEME, 0, 0, 0) == -1 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        let child = cmd.spawn().ok()?;
+        let main = child.id() as libc::pid_t;
+
+        let mut status = 0;
+        unsafe { libc::waitpid(main, &mut status, libc::__WALL) };
+        let options =
+            libc::PTRACE_O_TRACESYSGOOD | libc::PTRACE_O_TRACECLONE | libc::PTRACE_O_EXITKILL;
+        unsafe {
+            libc::ptrace(libc::PTRACE_SETOPTIONS, main, 0, options);
+            libc::ptrace(libc::PTRACE_SYSCALL, main, 0, 0);
+        }
+
+        let mut counts = [0u64; 6];
+        let mut in_syscall: HashMap<libc::pid_t, bool> = HashMap::new();
+        loop {
+            let pid = unsafe { libc::waitpid(-1, &mut status, libc::__WALL) };
+            if pid == -1 {
+                break;
+            }
+            if libc::WIFEXITED(status) || libc::WIFSIGNALED(status) {
+                if pid == main {
+                    break;
+                }
+                continue;
+            }
+
+            let sig = libc::WSTOPSIG(status);
+            let mut inject = 0;
+            if sig == libc::SIGTRAP | 0x80 {
+                let entering = !in_syscall.get(&pid).copied().unwrap_or(false);
+                in_syscall.insert(pid, entering);
+                if entering {
+                    let offset = (libc::ORIG_RAX * 8) as usize;
+                    let nr = unsafe { libc::ptrace(libc::PTRACE_PEEKUSER, pid, offset, 0) };
+                    if let Some(group) = get_group(nr) {
+                        counts[group] += 1;
+                    }
+                    counts[5] += 1;
+                }
+            } else if sig != libc::SIGTRAP && sig != libc::SIGSTOP {
+                inject = sig;
+            }
+            unsafe { libc::ptrace(libc::PTRACE_SYSCALL, pid, 0, inject) };
+        }
+
+        Some(counts)
+    }
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu", target_arch = "x86_64"))]
+fn report_syscalls(empty: &Path, fixture: &Path) {
+    println!(
+        "syscalls per entry ({} entries, startup cost subtracted)",
+        ENTRIES
+    );
+    println!(
+        "{:<10}{}",
+        "listing",
+        trace::GROUPS.map(|g| format!("{:>10}", g)).concat()
+    );
+
+    for (name, flags) in LISTINGS {
+        if !is_supported(empty, flags) {
+            println!("{:<10}not supported by this build", name);
+            continue;
+        }
+
+        let base = trace::count_syscalls(make_command(empty, flags));
+        let full = trace::count_syscalls(make_command(fixture, flags));
+        let (base, full) = match (base, full) {
+            (Some(b), Some(f)) => (b, f),
+            _ => {
+                println!("{:<10}ptrace unavailable", name);
+                continue;
+            }
+        };
+
+        let cells: String = (0..trace::GROUPS.len())
+            .map(|i| {
+                let per_entry = full[i].saturating_sub(base[i]) as f64 / ENTRIES as f64;
+                format!("{:>10.2}", per_entry)
+            })
+            .collect();
+        println!("{:<10}{}", name, cells);
+    }
+    println!();
+}
+
+#[cfg(not(all(target_os = "linux", target_env = "gnu", target_arch = "x86_64")))]
+fn report_syscalls(_empty: &Path, _fixture: &Path) {
+    println!("syscall counts need ptrace on x86_64 Linux, skipping\n");
+}
+
+fn main() {
+    // `cargo test --benches` passes no `--bench`; keep that run cheap
+    if !std::env::args().any(|a| a == "--bench") {
+        return;
+    }
+
+    let empty = TempDir::new().unwrap();
+    let fixture = TempDir::new().unwrap();
+    make_fixture(fixture.path(), ENTRIES);
+
+    println!("binary: {}\n", get_bin());
+    report_syscalls(empty.path(), fixture.path());
+
+    println!(
+        "median wall time over {} runs ({} entries)",
+        TIMING_RUNS, ENTRIES
+    );
+    for (name, flags) in LISTINGS {
+        if !is_supported(empty.path(), flags) {
+            println!("{:<10}not supported by this build", name);
+            continue;
+        }
+
+        let elapsed = time_listing(fixture.path(), flags);
+        println!("{:<10}{:>10.2?}", name, elapsed);
+    }
+}
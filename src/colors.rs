@@ -1,6 +1,6 @@
 #![allow(dead_code)]
 
-use crate::types::{ColType, FileRow, FileType};
+use crate::types::{ColType, FileRow, FileType, RenderOptions, TimeStyle};
 
 pub const ANSI_RED: &str = "\x1b[31m";
 pub const ANSI_MAGENTA: &str = "\x1b[35m";
@@ -10,64 +10,121 @@ pub const ANSI_LIGHT_GREEN: &str = "\x1b[92m";
 pub const ANSI_LIGHT_CYAN: &str = "\x1b[96m";
 pub const ANSI_LIGHT_YELLOW: &str = "\x1b[93m";
 pub const ANSI_LIGHT_GRAY: &str = "\x1b[37m";
-pub const ANSI_DARK_GRAY: &str = "\x1b[30m";
+pub const ANSI_DARK_GRAY: &str = "\x1b[90m";
 pub const ANSI_LIGHT_RED: &str = "\x1b[91m";
 pub const ANSI_LIGHT_BLUE: &str = "\x1b[94m";
 pub const ANSI_BLUE: &str = "\x1b[34m";
+pub const ANSI_CYAN: &str = "\x1b[36m";
+pub const ANSI_DIM_BLUE: &str = "\x1b[2;34m";
 pub const ANSI_END: &str = "\x1b[0m";
 
 pub fn add_color(text: &str, color_code: &str) -> String {
     format!("{}{}{}", color_code, text, ANSI_END)
 }
 
-pub fn get_color_for_field(row: &FileRow, field: ColType) -> &'static str {
-    match field {
-        ColType::TargetName => ANSI_LIGHT_CYAN,
-        ColType::SrcName => {
-            if row.info.ftype == FileType::Directory {
-                ANSI_LIGHT_RED
+const HOUR: i64 = 3600;
+const DAY: i64 = 24 * HOUR;
+
+// Fresh entries stand out and fade as they age
+pub fn get_age_key(age: i64) -> &'static str {
+    match age {
+        a if a < HOUR => "age.hour",
+        a if a < DAY => "age.day",
+        a if a < 7 * DAY => "age.week",
+        a if a < 30 * DAY => "age.month",
+        _ => "age.old",
+    }
+}
+
+fn get_time_key(row: &FileRow, opts: &RenderOptions) -> &'static str {
+    if opts.time.style != TimeStyle::Relative {
+        return "time";
+    }
+
+    match row.info.stat_res.get_time(opts.time.field) {
+        Some((secs, _)) => get_age_key(opts.now - secs),
+        None => "time",
+    }
+}
+
+fn get_name_key(row: &FileRow) -> &'static str {
+    match row.info.ftype {
+        FileType::Directory => "name.dir",
+        FileType::Symlink => "name.symlink",
+        FileType::BrokenSymlink => "name.broken",
+        FileType::Fifo => "name.fifo",
+        FileType::Socket => "name.socket",
+        FileType::BlockDevice | FileType::CharDevice => "name.device",
+        FileType::File => "name.file",
+    }
+}
+
+// A mixed status takes the color of its most pressing letter
+fn get_git_key(status: &str) -> &'static str {
+    let mut chars = status.chars();
+    let staged = chars.next().unwrap_or('-');
+    let unstaged = chars.next().unwrap_or('-');
+
+    match (staged, unstaged) {
+        ('U', _) | (_, 'U') => "git.conflicted",
+        ('?', '?') => "git.untracked",
+        ('!', '!') => "git.ignored",
+        (_, 'M' | 'D') => "git.modified",
+        ('A' | 'M' | 'D', _) => "git.staged",
+        (_, '?') => "git.untracked",
+        _ => "git.clean",
+    }
+}
+
+pub fn get_color_for_field<'a>(row: &FileRow, field: ColType, opts: &'a RenderOptions) -> &'a str {
+    // Ignored entries are dimmed as a whole row so they recede behind tracked files
+    if row.render.dimmed {
+        return opts.theme.get("ignored");
+    }
+
+    let key = match field {
+        ColType::TargetName => {
+            if row.info.ftype == FileType::BrokenSymlink {
+                "target.broken"
             } else {
-                ANSI_LIGHT_GREEN
+                "target"
             }
         }
-        ColType::TimeIso => ANSI_BLUE,
+        ColType::SrcName => {
+            let ls_color = opts.ls_colors.as_ref().and_then(|c| c.get_name_color(&row.info));
+            if let Some(code) = ls_color {
+                return code;
+            }
+            get_name_key(row)
+        }
+        ColType::TimeIso => get_time_key(row, opts),
+        ColType::GitStatus => get_git_key(&row.render.gitstatus),
         ColType::Size => {
-            if row.info.ftype == FileType::Directory {
-                ANSI_MAGENTA
+            if row.info.is_dir() {
+                "size.dir"
             } else {
-                ANSI_GREEN
+                "size"
             }
         }
-        ColType::Acls => ANSI_DARK_GRAY,
-        ColType::Owner => ANSI_DARK_GRAY,
-        ColType::FileType => ANSI_DARK_GRAY,
-        ColType::Preview => ANSI_DARK_GRAY,
-    }
+        ColType::Acls => "acls",
+        ColType::Owner => "owner",
+        ColType::FileType => "filetype",
+        ColType::Preview => "preview",
+    };
+
+    opts.theme.get(key)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::{ContentType, FileRowInfo, RenderedCols, StatResult};
-
-    fn make_test_row(ftype: FileType) -> FileRow {
-        let info = FileRowInfo {
-            fname: String::from("test"),
-            ftype,
-            stat_res: StatResult {
-                st_mode: 0o644,
-                st_mtime: 1704067200,
-                st_uid: 1000,
-                st_gid: 1000,
-                st_size: 1024,
-            },
-            content_type: ContentType::Text,
-            time_epoch: String::from("1704067200"),
-        };
-        FileRow {
-            info,
-            render: RenderedCols::default(),
-        }
+    use crate::ls_colors::LsColors;
+    use crate::theme::Theme;
+    use crate::types::fixtures::make_test_row;
+    use crate::types::TimeOptions;
+
+    fn default_color(row: &FileRow, field: ColType) -> String {
+        get_color_for_field(row, field, &RenderOptions::default()).to_string()
     }
 
     #[test]
@@ -90,61 +147,155 @@ mod tests {
 
     #[test]
     fn test_get_color_targetname() {
-        let row = make_test_row(FileType::File);
-        assert_eq!(get_color_for_field(&row, ColType::TargetName), ANSI_LIGHT_CYAN);
+        let row = make_test_row("test", FileType::File);
+        assert_eq!(default_color(&row, ColType::TargetName), ANSI_LIGHT_CYAN);
+    }
+
+    #[test]
+    fn test_get_color_targetname_broken() {
+        let row = make_test_row("test", FileType::BrokenSymlink);
+        assert_eq!(default_color(&row, ColType::TargetName), ANSI_RED);
     }
 
     #[test]
     fn test_get_color_srcname_directory() {
-        let row = make_test_row(FileType::Directory);
-        assert_eq!(get_color_for_field(&row, ColType::SrcName), ANSI_LIGHT_RED);
+        let row = make_test_row("test", FileType::Directory);
+        assert_eq!(default_color(&row, ColType::SrcName), ANSI_LIGHT_RED);
     }
 
     #[test]
     fn test_get_color_srcname_file() {
-        let row = make_test_row(FileType::File);
-        assert_eq!(get_color_for_field(&row, ColType::SrcName), ANSI_LIGHT_GREEN);
+        let row = make_test_row("test", FileType::File);
+        assert_eq!(default_color(&row, ColType::SrcName), ANSI_LIGHT_GREEN);
+    }
+
+    #[test]
+    fn test_get_color_srcname_special() {
+        let cases = [
+            (FileType::Symlink, ANSI_LIGHT_CYAN),
+            (FileType::BrokenSymlink, ANSI_RED),
+            (FileType::Fifo, ANSI_LIGHT_YELLOW),
+            (FileType::Socket, ANSI_LIGHT_MAGENTA),
+            (FileType::BlockDevice, ANSI_LIGHT_BLUE),
+            (FileType::CharDevice, ANSI_LIGHT_BLUE),
+        ];
+        for (ftype, color) in cases {
+            let row = make_test_row("test", ftype);
+            assert_eq!(default_color(&row, ColType::SrcName), color);
+        }
+    }
+
+    #[test]
+    fn test_get_color_dimmed_row() {
+        let mut row = make_test_row("test", FileType::Directory);
+        row.render.dimmed = true;
+        let dim = Theme::dark().get("ignored").to_string();
+        assert_eq!(default_color(&row, ColType::SrcName), dim);
+        assert_eq!(default_color(&row, ColType::Size), dim);
+    }
+
+    #[test]
+    fn test_get_git_key() {
+        assert_eq!(get_git_key("--"), "git.clean");
+        assert_eq!(get_git_key("UU"), "git.conflicted");
+        assert_eq!(get_git_key("??"), "git.untracked");
+        assert_eq!(get_git_key("-?"), "git.untracked");
+        assert_eq!(get_git_key("!!"), "git.ignored");
+        assert_eq!(get_git_key("AM"), "git.modified");
+        assert_eq!(get_git_key("M-"), "git.staged");
+        assert_eq!(get_git_key(" "), "git.clean");
     }
 
     #[test]
     fn test_get_color_size_directory() {
-        let row = make_test_row(FileType::Directory);
-        assert_eq!(get_color_for_field(&row, ColType::Size), ANSI_MAGENTA);
+        let row = make_test_row("test", FileType::Directory);
+        assert_eq!(default_color(&row, ColType::Size), ANSI_MAGENTA);
     }
 
     #[test]
     fn test_get_color_size_file() {
-        let row = make_test_row(FileType::File);
-        assert_eq!(get_color_for_field(&row, ColType::Size), ANSI_GREEN);
+        let row = make_test_row("test", FileType::File);
+        assert_eq!(default_color(&row, ColType::Size), ANSI_GREEN);
     }
 
     #[test]
     fn test_get_color_timeiso() {
-        let row = make_test_row(FileType::File);
-        assert_eq!(get_color_for_field(&row, ColType::TimeIso), ANSI_BLUE);
+        let row = make_test_row("test", FileType::File);
+        assert_eq!(default_color(&row, ColType::TimeIso), ANSI_BLUE);
     }
 
     #[test]
     fn test_get_color_acls() {
-        let row = make_test_row(FileType::File);
-        assert_eq!(get_color_for_field(&row, ColType::Acls), ANSI_DARK_GRAY);
+        let row = make_test_row("test", FileType::File);
+        assert_eq!(default_color(&row, ColType::Acls), ANSI_DARK_GRAY);
     }
 
     #[test]
     fn test_get_color_owner() {
-        let row = make_test_row(FileType::File);
-        assert_eq!(get_color_for_field(&row, ColType::Owner), ANSI_DARK_GRAY);
+        let row = make_test_row("test", FileType::File);
+        assert_eq!(default_color(&row, ColType::Owner), ANSI_DARK_GRAY);
     }
 
     #[test]
     fn test_get_color_filetype() {
-        let row = make_test_row(FileType::File);
-        assert_eq!(get_color_for_field(&row, ColType::FileType), ANSI_DARK_GRAY);
+        let row = make_test_row("test", FileType::File);
+        assert_eq!(default_color(&row, ColType::FileType), ANSI_DARK_GRAY);
     }
 
     #[test]
     fn test_get_color_preview() {
-        let row = make_test_row(FileType::File);
-        assert_eq!(get_color_for_field(&row, ColType::Preview), ANSI_DARK_GRAY);
+        let row = make_test_row("test", FileType::File);
+        assert_eq!(default_color(&row, ColType::Preview), ANSI_DARK_GRAY);
+    }
+
+    #[test]
+    fn test_get_age_key_grades() {
+        assert_eq!(get_age_key(0), "age.hour");
+        assert_eq!(get_age_key(5 * HOUR), "age.day");
+        assert_eq!(get_age_key(3 * DAY), "age.week");
+        assert_eq!(get_age_key(12 * DAY), "age.month");
+        assert_eq!(get_age_key(400 * DAY), "age.old");
+    }
+
+    #[test]
+    fn test_get_color_timeiso_relative_graded() {
+        let row = make_test_row("test", FileType::File);
+        let opts = RenderOptions {
+            time: TimeOptions {
+                style: TimeStyle::Relative,
+                ..TimeOptions::default()
+            },
+            now: 1704067200 + 60,
+            ..RenderOptions::default()
+        };
+        assert_eq!(get_color_for_field(&row, ColType::TimeIso, &opts), ANSI_LIGHT_CYAN);
+
+        let stale = RenderOptions {
+            now: 1704067200 + 365 * DAY,
+            ..opts
+        };
+        assert_eq!(get_color_for_field(&row, ColType::TimeIso, &stale), ANSI_DIM_BLUE);
+    }
+
+    #[test]
+    fn test_get_color_srcname_ls_colors() {
+        let opts = RenderOptions {
+            ls_colors: Some(LsColors::parse("di=01;34")),
+            ..RenderOptions::default()
+        };
+        let dir = make_test_row("test", FileType::Directory);
+        let file = make_test_row("test", FileType::File);
+        assert_eq!(get_color_for_field(&dir, ColType::SrcName, &opts), "\x1b[01;34m");
+        assert_eq!(get_color_for_field(&file, ColType::SrcName, &opts), ANSI_LIGHT_GREEN);
+    }
+
+    #[test]
+    fn test_get_color_uses_theme() {
+        let opts = RenderOptions {
+            theme: Theme::light(),
+            ..RenderOptions::default()
+        };
+        let row = make_test_row("test", FileType::Directory);
+        assert_eq!(get_color_for_field(&row, ColType::SrcName, &opts), "\x1b[1;34m");
     }
 }
@@ -3,11 +3,21 @@
 use std::fs;
 use std::path::Path;
 
+use crate::git_status::GitRepo;
 use crate::permissions::{col_acls, UserGroupCache};
 use crate::preview::{preview_binary, preview_directory, preview_text};
-use crate::types::{Align, ColDef, ColType, ContentType, FileRowInfo, FileType};
-use crate::utils::{format_size_with_commas, format_timestamp, truncate_middle};
-
+use crate::types::{
+    Align, ColDef, ColType, ContentType, FileRowInfo, FileType, SizeFormat, TimeOptions,
+};
+use crate::utils::{
+    format_size_blocks, format_size_human, format_size_iec, format_size_si,
+    format_size_with_commas, format_time,
+};
+
+const BROKEN_LINK_MARKER: &str = "[broken]";
+
+// The git column is left out: it reads the repository's index, so it only shows when
+// picked with `--columns git`
 pub fn get_col_defs() -> Vec<ColDef> {
     vec![
         ColDef {
@@ -54,8 +64,8 @@ pub fn get_col_defs() -> Vec<ColDef> {
 }
 
 pub fn render_col_acls(info: &FileRowInfo) -> String {
-    let path = Path::new(&info.fname);
-    col_acls(path, info.stat_res.st_mode)
+    let followed_mode = info.get_followed_stat().map(|s| s.st_mode);
+    col_acls(Path::new(&info.fname), info.stat_res.st_mode, followed_mode)
 }
 
 pub fn render_col_owner(info: &FileRowInfo, cache: &UserGroupCache) -> String {
@@ -64,7 +74,17 @@ pub fn render_col_owner(info: &FileRowInfo, cache: &UserGroupCache) -> String {
     format!("{}:{}", user, group)
 }
 
+// `b` is taken by binary files, so block devices use the `k` of block
 pub fn render_col_filetype(info: &FileRowInfo) -> String {
+    match info.ftype {
+        FileType::BrokenSymlink => return String::from("l"),
+        FileType::Fifo => return String::from("p"),
+        FileType::Socket => return String::from("s"),
+        FileType::BlockDevice => return String::from("k"),
+        FileType::CharDevice => return String::from("c"),
+        _ => {}
+    }
+
     match info.content_type {
         ContentType::Directory => String::from("d"),
         ContentType::BinaryExecutable => String::from("e"),
@@ -74,38 +94,40 @@ pub fn render_col_filetype(info: &FileRowInfo) -> String {
     }
 }
 
-pub fn render_col_size(info: &FileRowInfo) -> String {
-    if info.ftype == FileType::Directory {
-        get_subfile_count(&info.fname)
-    } else {
-        format_size_with_commas(info.stat_res.st_size)
+pub fn render_col_size(info: &FileRowInfo, size_format: SizeFormat) -> String {
+    if info.is_dir() {
+        return get_subfile_count(&info.fname);
     }
-}
-
-fn get_subfile_count(fname: &str) -> String {
-    let path = Path::new(fname);
-
-    let real_path = if path.is_symlink() {
-        match fs::canonicalize(path) {
-            Ok(p) => p,
-            Err(_) => return String::from("-"),
-        }
-    } else {
-        path.to_path_buf()
-    };
 
-    if !real_path.is_dir() {
-        return String::from("-");
+    let size = info.stat_res.st_size;
+    match size_format {
+        SizeFormat::Bytes => format_size_with_commas(size),
+        SizeFormat::Human => format_size_human(size),
+        SizeFormat::Si => format_size_si(size),
+        SizeFormat::Iec => format_size_iec(size),
+        SizeFormat::Blocks => format_size_blocks(info.stat_res.st_blocks),
     }
+}
 
-    match fs::read_dir(&real_path) {
+// Only called for rows already known to be directories; read_dir follows symlinks itself
+fn get_subfile_count(fname: &str) -> String {
+    match fs::read_dir(fname) {
         Ok(entries) => entries.count().to_string(),
         Err(_) => String::from("-"),
     }
 }
 
-pub fn render_col_timeiso(info: &FileRowInfo) -> String {
-    format_timestamp(info.stat_res.st_mtime)
+pub fn render_col_timeiso(info: &FileRowInfo, opts: &TimeOptions, now: i64) -> String {
+    match info.stat_res.get_time(opts.field) {
+        Some((secs, nsec)) => format_time(secs, nsec, &opts.style, opts.utc, now),
+        None => String::from("-"),
+    }
+}
+
+pub fn render_col_gitstatus(info: &FileRowInfo, repo: Option<&GitRepo>) -> String {
+    repo.and_then(|r| r.get_status(Path::new(&info.fname)))
+        .map(|status| status.to_string())
+        .unwrap_or_else(|| String::from(" "))
 }
 
 pub fn render_col_srcname(info: &FileRowInfo) -> String {
@@ -115,34 +137,31 @@ pub fn render_col_srcname(info: &FileRowInfo) -> String {
         .map(|n| n.to_string_lossy().to_string())
         .unwrap_or_else(|| info.fname.clone());
 
-    if info.ftype == FileType::Directory {
-        format!("{}/", name)
-    } else {
-        name
+    match info.ftype {
+        FileType::Directory => format!("{}/", name),
+        FileType::Symlink | FileType::BrokenSymlink => format!("{}@", name),
+        FileType::Fifo => format!("{}|", name),
+        FileType::Socket => format!("{}=", name),
+        _ => name,
     }
 }
 
 pub fn render_col_targetname(info: &FileRowInfo) -> String {
-    let path = Path::new(&info.fname);
-
-    if !path.is_symlink() {
-        return String::from(" ");
-    }
-
-    let real_path = match fs::read_link(path) {
-        Ok(p) => p,
-        Err(_) => return String::from(" "),
+    let target = match &info.link_target {
+        Some(t) => t.clone(),
+        None => return String::from(" "),
     };
 
-    let target = real_path.to_string_lossy().to_string();
+    if info.ftype == FileType::BrokenSymlink {
+        return format!("{} {}", target, BROKEN_LINK_MARKER);
+    }
 
-    let full = if info.ftype == FileType::Directory {
+    // Left whole; only fitting rows to a terminal shortens it
+    if info.is_dir() {
         format!("{}/", target)
     } else {
         target
-    };
-
-    truncate_middle(&full, 25)
+    }
 }
 
 pub fn render_col_preview(info: &FileRowInfo) -> String {
@@ -157,27 +176,12 @@ pub fn render_col_preview(info: &FileRowInfo) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::StatResult;
+    use crate::types::fixtures::make_test_info;
+    use crate::types::{StatResult, TimeField, TimeStyle};
     use tempfile::TempDir;
     use std::fs::File;
     use std::io::Write;
 
-    fn make_test_info(fname: &str, ftype: FileType, content_type: ContentType) -> FileRowInfo {
-        FileRowInfo {
-            fname: String::from(fname),
-            ftype,
-            stat_res: StatResult {
-                st_mode: 0o644,
-                st_mtime: 1704067200,
-                st_uid: 1000,
-                st_gid: 1000,
-                st_size: 1024,
-            },
-            content_type,
-            time_epoch: String::from("1704067200"),
-        }
-    }
-
     #[test]
     fn test_get_col_defs_count() {
         let defs = get_col_defs();
@@ -242,7 +246,7 @@ mod tests {
 
         let mut info = make_test_info(file_path.to_str().unwrap(), FileType::File, ContentType::Text);
         info.stat_res.st_size = 14;
-        let result = render_col_size(&info);
+        let result = render_col_size(&info, SizeFormat::Bytes);
         assert_eq!(result, "14");
     }
 
@@ -253,18 +257,64 @@ mod tests {
         File::create(dir.path().join("file2.txt")).unwrap();
 
         let info = make_test_info(dir.path().to_str().unwrap(), FileType::Directory, ContentType::Directory);
-        let result = render_col_size(&info);
+        let result = render_col_size(&info, SizeFormat::Bytes);
         assert_eq!(result, "2");
     }
 
+    #[test]
+    fn test_render_col_size_symlinked_directory() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("real")).unwrap();
+        File::create(dir.path().join("real/a.txt")).unwrap();
+        let link_path = dir.path().join("link");
+        std::os::unix::fs::symlink("real", &link_path).unwrap();
+
+        let info = make_test_info(link_path.to_str().unwrap(), FileType::Symlink, ContentType::Directory);
+        assert_eq!(render_col_size(&info, SizeFormat::Bytes), "1");
+    }
+
+    #[test]
+    fn test_render_col_size_formats() {
+        let mut info = make_test_info("big.iso", FileType::File, ContentType::BinaryOther);
+        info.stat_res.st_size = 1_234_567_890;
+        info.stat_res.st_blocks = 2_411_264;
+        assert_eq!(render_col_size(&info, SizeFormat::Bytes), "1,234,567,890");
+        assert_eq!(render_col_size(&info, SizeFormat::Human), "1.1G");
+        assert_eq!(render_col_size(&info, SizeFormat::Si), "1.2 GB");
+        assert_eq!(render_col_size(&info, SizeFormat::Iec), "1.1 GiB");
+        assert_eq!(render_col_size(&info, SizeFormat::Blocks), "1,205,632");
+    }
+
     #[test]
     fn test_render_col_timeiso_format() {
         let info = make_test_info("test", FileType::File, ContentType::Text);
-        let result = render_col_timeiso(&info);
+        let result = render_col_timeiso(&info, &TimeOptions::default(), 0);
         assert!(result.contains('-'));
         assert!(result.contains(':'));
     }
 
+    #[test]
+    fn test_render_col_timeiso_field_and_style() {
+        let mut info = make_test_info("test", FileType::File, ContentType::Text);
+        info.stat_res.st_atime = 1704067260;
+        let opts = TimeOptions {
+            style: TimeStyle::Relative,
+            field: TimeField::Atime,
+            utc: true,
+        };
+        assert_eq!(render_col_timeiso(&info, &opts, 1704067320), "1m ago");
+    }
+
+    #[test]
+    fn test_render_col_timeiso_missing_btime() {
+        let info = make_test_info("test", FileType::File, ContentType::Text);
+        let opts = TimeOptions {
+            field: TimeField::Btime,
+            ..TimeOptions::default()
+        };
+        assert_eq!(render_col_timeiso(&info, &opts, 0), "-");
+    }
+
     #[test]
     fn test_render_col_srcname_file() {
         let info = make_test_info("/path/to/file.txt", FileType::File, ContentType::Text);
@@ -280,16 +330,66 @@ mod tests {
     }
 
     #[test]
-    fn test_render_col_targetname_not_symlink() {
-        let dir = TempDir::new().unwrap();
-        let file_path = dir.path().join("regular.txt");
-        File::create(&file_path).unwrap();
+    fn test_render_col_srcname_special_suffixes() {
+        let link = make_test_info("/a/link", FileType::Symlink, ContentType::Text);
+        let fifo = make_test_info("/a/pipe", FileType::Fifo, ContentType::Other);
+        let sock = make_test_info("/a/sock", FileType::Socket, ContentType::Other);
+        let dev = make_test_info("/dev/null", FileType::CharDevice, ContentType::Other);
+        assert_eq!(render_col_srcname(&link), "link@");
+        assert_eq!(render_col_srcname(&fifo), "pipe|");
+        assert_eq!(render_col_srcname(&sock), "sock=");
+        assert_eq!(render_col_srcname(&dev), "null");
+    }
 
-        let info = make_test_info(file_path.to_str().unwrap(), FileType::File, ContentType::Text);
+    #[test]
+    fn test_render_col_filetype_special() {
+        let fifo = make_test_info("p", FileType::Fifo, ContentType::Other);
+        let sock = make_test_info("s", FileType::Socket, ContentType::Other);
+        let blk = make_test_info("b", FileType::BlockDevice, ContentType::Other);
+        let chr = make_test_info("c", FileType::CharDevice, ContentType::Other);
+        let broken = make_test_info("l", FileType::BrokenSymlink, ContentType::NotReadable);
+        assert_eq!(render_col_filetype(&fifo), "p");
+        assert_eq!(render_col_filetype(&sock), "s");
+        assert_eq!(render_col_filetype(&blk), "k");
+        assert_eq!(render_col_filetype(&chr), "c");
+        assert_eq!(render_col_filetype(&broken), "l");
+    }
+
+    #[test]
+    fn test_render_col_filetype_symlink_uses_content() {
+        let info = make_test_info("l", FileType::Symlink, ContentType::Text);
+        assert_eq!(render_col_filetype(&info), "t");
+    }
+
+    #[test]
+    fn test_render_col_targetname_not_symlink() {
+        let info = make_test_info("regular.txt", FileType::File, ContentType::Text);
         let result = render_col_targetname(&info);
         assert_eq!(result, " ");
     }
 
+    #[test]
+    fn test_render_col_targetname_symlink() {
+        let mut info = make_test_info("link", FileType::Symlink, ContentType::Empty);
+        info.link_target = Some(String::from("real.txt"));
+        assert_eq!(render_col_targetname(&info), "real.txt");
+    }
+
+    #[test]
+    fn test_render_col_targetname_long_kept_whole() {
+        let mut info = make_test_info("link", FileType::Symlink, ContentType::Empty);
+        let target = "/very/long/path/to/some/deeply/nested/inside.txt";
+        info.link_target = Some(String::from(target));
+        assert_eq!(render_col_targetname(&info), target);
+    }
+
+    #[test]
+    fn test_render_col_targetname_broken() {
+        let mut info = make_test_info("dead", FileType::BrokenSymlink, ContentType::NotReadable);
+        info.link_target = Some(String::from("missing.txt"));
+        assert_eq!(render_col_targetname(&info), "missing.txt [broken]");
+    }
+
     #[test]
     fn test_render_col_preview_unknown() {
         let info = make_test_info("test", FileType::File, ContentType::Unknown);
@@ -297,6 +397,28 @@ mod tests {
         assert_eq!(result, " ");
     }
 
+    #[test]
+    fn test_render_col_acls_symlink_uses_target_mode() {
+        let dir = TempDir::new().unwrap();
+        let file_path = dir.path().join("real.txt");
+        File::create(&file_path).unwrap();
+        let link_path = dir.path().join("link");
+        std::os::unix::fs::symlink("real.txt", &link_path).unwrap();
+
+        let fname = link_path.to_str().unwrap();
+        let mut info = make_test_info(fname, FileType::Symlink, ContentType::Empty);
+        info.stat_res.st_mode = 0o120777;
+        info.link_stat = Some(StatResult {
+            st_mode: 0o100644,
+            ..StatResult::default()
+        });
+        assert_eq!(render_col_acls(&info), "777 6");
+
+        info.ftype = FileType::BrokenSymlink;
+        info.link_stat = None;
+        assert_eq!(render_col_acls(&info), "777 0");
+    }
+
     #[test]
     fn test_render_col_owner_format() {
         let dir = TempDir::new().unwrap();
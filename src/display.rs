@@ -1,34 +1,212 @@
 use std::io::Write;
+use std::os::unix::process::ExitStatusExt;
 use std::process::{Command, Stdio};
 
-pub fn paged_display(output: &str) {
-    let formatted = format!("\n{}\n\n", output);
-
-    // @TODO: Replace unix `less` with a Rust lib solution
-    let mut child = match Command::new("less")
-        .args([
-            "--RAW-CONTROL-CHARS",
-            "--quit-at-eof",
-            "--quit-if-one-screen",
-            "--no-init",
-        ])
-        .stdin(Stdio::piped())
-        .spawn()
-    {
+use crate::pager::{page, PageError};
+use crate::terminal::is_stdout_tty;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PagerChoice {
+    Builtin,
+    External(String),
+    Off,
+}
+
+// $ELS_PAGER beats $PAGER; setting it empty or to `cat` turns paging off
+pub fn resolve_pager(no_pager: bool, els_pager: Option<&str>, pager: Option<&str>) -> PagerChoice {
+    if no_pager {
+        return PagerChoice::Off;
+    }
+
+    let command = match els_pager {
+        Some(cmd) => cmd.trim(),
+        None => match pager.map(str::trim).filter(|cmd| !cmd.is_empty()) {
+            Some(cmd) => cmd,
+            None => return PagerChoice::Builtin,
+        },
+    };
+
+    match command {
+        "" | "cat" => PagerChoice::Off,
+        cmd => PagerChoice::External(cmd.to_string()),
+    }
+}
+
+// Shell-style status: the exit code, or 128 plus the signal that killed the pager
+fn exit_code(status: std::process::ExitStatus) -> i32 {
+    status
+        .code()
+        .or_else(|| status.signal().map(|sig| 128 + sig))
+        .unwrap_or(1)
+}
+
+// Asked before spawning, since a pager that ran may exit 126 or 127 on its own.
+// Assignments, quoting and expansions are left for sh to sort out.
+fn command_exists(command: &str) -> bool {
+    let program = match command.split_whitespace().next() {
+        Some(p) => p,
+        None => return false,
+    };
+    if program.contains(['=', '"', '\'', '$']) {
+        return true;
+    }
+
+    Command::new("sh")
+        .args(["-c", "command -v -- \"$1\"", "sh", program])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+// Runs through `sh -c` so the command can carry its own arguments, like git does
+fn external_display(command: &str, output: &str) -> i32 {
+    if !command_exists(command) {
+        eprintln!("Error: cannot run pager '{}': command not found", command);
+        write_stdout(output);
+        return 1;
+    }
+
+    let mut cmd = Command::new("sh");
+    cmd.args(["-c", command]).stdin(Stdio::piped());
+
+    // Keep less behaving as it did when els always ran it with these flags
+    if std::env::var_os("LESS").is_none() {
+        cmd.env("LESS", "eFRX");
+    }
+
+    let mut child = match cmd.spawn() {
         Ok(c) => c,
-        Err(_) => {
-            print!("{}", formatted);
-            return;
+        Err(e) => {
+            eprintln!("Error: cannot run pager '{}': {}", command, e);
+            write_stdout(output);
+            return 1;
         }
     };
 
-    if let Some(ref mut stdin) = child.stdin {
-        let _ = stdin.write_all(formatted.as_bytes());
+    // The pager may quit before reading everything; the broken pipe is expected
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(output.as_bytes());
     }
 
-    let _ = child.wait();
+    match child.wait() {
+        Ok(status) => exit_code(status),
+        Err(_) => 1,
+    }
 }
 
-pub fn display(rows_str: &str) {
-    paged_display(rows_str);
+pub fn paged_display(output: &str, choice: &PagerChoice) -> i32 {
+    let formatted = || format!("\n{}\n\n", output);
+
+    match choice {
+        PagerChoice::External(command) => external_display(command, &formatted()),
+        PagerChoice::Builtin => match page(&formatted()) {
+            Ok(()) => 0,
+            // Without a controlling terminal to read keys from, fall back to plain output
+            Err(PageError::NoTerminal) => {
+                write_stdout(&formatted());
+                0
+            }
+            Err(PageError::Io(e)) => {
+                eprintln!("Error: {}", e);
+                1
+            }
+        },
+        PagerChoice::Off => {
+            write_rows(output);
+            0
+        }
+    }
+}
+
+// A reader that stops early (`els | head`) closes the pipe; that is not an error worth a panic
+pub fn write_stdout(output: &str) {
+    let _ = std::io::stdout().write_all(output.as_bytes());
+}
+
+// The bare rows, without the pager's framing blank lines
+fn write_rows(rows_str: &str) {
+    if !rows_str.is_empty() {
+        write_stdout(&format!("{}\n", rows_str));
+    }
+}
+
+pub fn display(rows_str: &str, no_pager: bool) -> i32 {
+    if !is_stdout_tty() {
+        write_rows(rows_str);
+        return 0;
+    }
+
+    let els_pager = std::env::var("ELS_PAGER").ok();
+    let pager = std::env::var("PAGER").ok();
+    let choice = resolve_pager(no_pager, els_pager.as_deref(), pager.as_deref());
+    paged_display(rows_str, &choice)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_pager_default_builtin() {
+        assert_eq!(resolve_pager(false, None, None), PagerChoice::Builtin);
+        assert_eq!(resolve_pager(false, None, Some("  ")), PagerChoice::Builtin);
+    }
+
+    #[test]
+    fn test_resolve_pager_no_pager_wins() {
+        assert_eq!(
+            resolve_pager(true, Some("moar"), Some("less")),
+            PagerChoice::Off
+        );
+    }
+
+    #[test]
+    fn test_resolve_pager_env_precedence() {
+        assert_eq!(
+            resolve_pager(false, Some("bat --plain"), Some("less")),
+            PagerChoice::External(String::from("bat --plain"))
+        );
+        assert_eq!(
+            resolve_pager(false, None, Some("moar")),
+            PagerChoice::External(String::from("moar"))
+        );
+    }
+
+    #[test]
+    fn test_resolve_pager_disabled_by_env() {
+        assert_eq!(
+            resolve_pager(false, Some(""), Some("less")),
+            PagerChoice::Off
+        );
+        assert_eq!(resolve_pager(false, None, Some("cat")), PagerChoice::Off);
+    }
+
+    #[test]
+    fn test_external_display_exit_status() {
+        assert_eq!(external_display("cat > /dev/null", "rows"), 0);
+        assert_eq!(external_display("cat > /dev/null; exit 3", "rows"), 3);
+    }
+
+    #[test]
+    fn test_external_display_missing_command() {
+        assert!(!command_exists("els-test-no-such-pager --flag"));
+        assert_eq!(external_display("els-test-no-such-pager", ""), 1);
+    }
+
+    #[test]
+    fn test_external_display_pager_exit_126_127() {
+        assert!(command_exists("cat -u"));
+        assert!(command_exists("LESS=R less"));
+        assert_eq!(external_display("cat > /dev/null; exit 127", "rows"), 127);
+        assert_eq!(external_display("cat > /dev/null; exit 126", "rows"), 126);
+    }
+
+    #[test]
+    fn test_external_display_signal_status() {
+        assert_eq!(
+            external_display("kill -TERM $$", "rows"),
+            128 + libc::SIGTERM
+        );
+    }
 }
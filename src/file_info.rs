@@ -1,54 +1,171 @@
-use std::fs;
-use std::os::unix::fs::MetadataExt;
+use std::ffi::CString;
+use std::fs::{self, File};
+use std::io;
+use std::mem::MaybeUninit;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
 use std::path::Path;
 
-use mimetype_detector::detect_file;
+use mimetype_detector::detect_reader;
 
-use crate::types::{ContentType, FileRowInfo, FileType, StatResult};
+use crate::types::{ContentType, FileRowInfo, FileType, InfoNeeds, StatResult};
 
-pub fn get_stat_result(path: &Path) -> Option<StatResult> {
-    let metadata = fs::symlink_metadata(path).ok()?;
-    Some(StatResult {
-        st_mode: metadata.mode(),
-        st_mtime: metadata.mtime(),
-        st_uid: metadata.uid(),
-        st_gid: metadata.gid(),
-        st_size: metadata.size(),
+// Asks statx for the birth time itself: std only does so on glibc, and the release builds are musl
+fn get_statx(path: &Path, flags: libc::c_int) -> io::Result<StatResult> {
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+    let mask = libc::STATX_BASIC_STATS | libc::STATX_BTIME;
+    let mut buf = MaybeUninit::<libc::statx>::zeroed();
+    let ret = unsafe {
+        libc::statx(libc::AT_FDCWD, c_path.as_ptr(), flags, mask, buf.as_mut_ptr())
+    };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let stx = unsafe { buf.assume_init() };
+    // Filesystems without birth times leave the bit out of the returned mask
+    let btime = (stx.stx_mask & libc::STATX_BTIME != 0).then_some(stx.stx_btime);
+
+    Ok(StatResult {
+        st_mode: u32::from(stx.stx_mode),
+        st_mtime: stx.stx_mtime.tv_sec,
+        st_uid: stx.stx_uid,
+        st_gid: stx.stx_gid,
+        st_size: stx.stx_size,
+        st_blocks: stx.stx_blocks,
+        st_mtime_nsec: i64::from(stx.stx_mtime.tv_nsec),
+        st_atime: stx.stx_atime.tv_sec,
+        st_atime_nsec: i64::from(stx.stx_atime.tv_nsec),
+        st_ctime: stx.stx_ctime.tv_sec,
+        st_ctime_nsec: i64::from(stx.stx_ctime.tv_nsec),
+        st_btime: btime.map(|t| t.tv_sec),
+        st_btime_nsec: btime.map(|t| i64::from(t.tv_nsec)).unwrap_or(0),
     })
 }
 
-pub fn get_file_type(path: &Path) -> FileType {
-    let real_path = if path.is_symlink() {
-        fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+// Plain stat fields for when statx is unavailable; these carry no birth time
+fn from_metadata(meta: &fs::Metadata) -> StatResult {
+    StatResult {
+        st_mode: meta.mode(),
+        st_mtime: meta.mtime(),
+        st_uid: meta.uid(),
+        st_gid: meta.gid(),
+        st_size: meta.size(),
+        st_blocks: meta.blocks(),
+        st_mtime_nsec: meta.mtime_nsec(),
+        st_atime: meta.atime(),
+        st_atime_nsec: meta.atime_nsec(),
+        st_ctime: meta.ctime(),
+        st_ctime_nsec: meta.ctime_nsec(),
+        st_btime: None,
+        st_btime_nsec: 0,
+    }
+}
+
+// Kernels before 4.11 lack statx and some seccomp profiles refuse it; lstat/stat still work there
+fn or_fallback_stat(
+    result: io::Result<StatResult>,
+    path: &Path,
+    follow: bool,
+) -> Option<StatResult> {
+    match result {
+        Ok(stat_res) => Some(stat_res),
+        Err(e) if matches!(e.raw_os_error(), Some(libc::ENOSYS | libc::EPERM)) => {
+            let meta = if follow {
+                fs::metadata(path)
+            } else {
+                fs::symlink_metadata(path)
+            };
+            meta.ok().map(|m| from_metadata(&m))
+        }
+        Err(_) => None,
+    }
+}
+
+pub fn get_stat_result(path: &Path) -> Option<StatResult> {
+    or_fallback_stat(get_statx(path, libc::AT_SYMLINK_NOFOLLOW), path, false)
+}
+
+// Follows symlinks, like fs::metadata
+fn get_followed_stat(path: &Path) -> Option<StatResult> {
+    or_fallback_stat(get_statx(path, 0), path, true)
+}
+
+// Just the type bits, for listings that never look at the rest of a stat
+fn to_type_stat(file_type: fs::FileType) -> StatResult {
+    let st_mode = if file_type.is_dir() {
+        libc::S_IFDIR
+    } else if file_type.is_symlink() {
+        libc::S_IFLNK
+    } else if file_type.is_fifo() {
+        libc::S_IFIFO
+    } else if file_type.is_socket() {
+        libc::S_IFSOCK
+    } else if file_type.is_block_device() {
+        libc::S_IFBLK
+    } else if file_type.is_char_device() {
+        libc::S_IFCHR
     } else {
-        path.to_path_buf()
+        libc::S_IFREG
     };
 
-    if real_path.is_dir() {
-        FileType::Directory
-    } else {
-        FileType::File
+    StatResult {
+        st_mode,
+        ..StatResult::default()
     }
 }
 
-pub fn get_content_type(path: &Path, stat_res: &StatResult) -> ContentType {
-    if path.is_dir() {
-        return ContentType::Directory;
+fn is_symlink(stat_res: &StatResult) -> bool {
+    stat_res.st_mode & libc::S_IFMT == libc::S_IFLNK
+}
+
+// `link_stat` is the followed stat of a symlink, None when the target is missing
+pub fn get_file_type(stat_res: &StatResult, link_stat: Option<&StatResult>) -> FileType {
+    match stat_res.st_mode & libc::S_IFMT {
+        libc::S_IFDIR => FileType::Directory,
+        libc::S_IFLNK => {
+            if link_stat.is_some() {
+                FileType::Symlink
+            } else {
+                FileType::BrokenSymlink
+            }
+        }
+        libc::S_IFIFO => FileType::Fifo,
+        libc::S_IFSOCK => FileType::Socket,
+        libc::S_IFBLK => FileType::BlockDevice,
+        libc::S_IFCHR => FileType::CharDevice,
+        _ => FileType::File,
     }
+}
+
+// Works from the stat already taken, so the only syscalls left are one open and one read
+pub fn get_content_type(path: &Path, followed: Option<&StatResult>) -> ContentType {
+    let stat_res = match followed {
+        Some(s) => s,
+        None => return ContentType::NotReadable,
+    };
 
-    if fs::File::open(path).is_err() {
-        return ContentType::NotReadable;
+    match stat_res.st_mode & libc::S_IFMT {
+        libc::S_IFDIR => return ContentType::Directory,
+        libc::S_IFREG => {}
+        // Opening a fifo or device to sniff it could block or have side effects
+        _ => return ContentType::Other,
     }
 
+    let file = match File::open(path) {
+        Ok(f) => f,
+        Err(_) => return ContentType::NotReadable,
+    };
+
     if stat_res.st_size == 0 {
         return ContentType::Empty;
     }
 
-    get_file_info_via_crate(path)
+    get_file_info_via_crate(file)
 }
 
-fn get_file_info_via_crate(path: &Path) -> ContentType {
-    let mime = match detect_file(path) {
+fn get_file_info_via_crate(file: File) -> ContentType {
+    let mime = match detect_reader(file) {
         Ok(m) => m,
         Err(_) => return ContentType::Unknown,
     };
@@ -66,11 +183,44 @@ fn get_file_info_via_crate(path: &Path) -> ContentType {
     ContentType::BinaryOther
 }
 
-pub fn get_row_info(fname: &str) -> Option<FileRowInfo> {
+// At most one statx per entry, skipped when `entry_type` from readdir is enough. A symlink
+// adds a second statx of its target when anything tells broken links or links to directories
+// apart, and a readlink when its target text is shown
+pub fn get_row_info(
+    fname: &str,
+    needs: InfoNeeds,
+    entry_type: Option<fs::FileType>,
+) -> Option<FileRowInfo> {
     let path = Path::new(fname);
-    let stat_res = get_stat_result(path)?;
-    let ftype = get_file_type(path);
-    let content_type = get_content_type(path, &stat_res);
+    let stat_res = match entry_type.filter(|_| !needs.stat) {
+        Some(file_type) => to_type_stat(file_type),
+        None => get_stat_result(path)?,
+    };
+
+    let is_link = is_symlink(&stat_res);
+    let follow = is_link && needs.link_stat;
+    let link_stat = if follow { get_followed_stat(path) } else { None };
+    let link_target = if is_link && needs.link_target {
+        fs::read_link(path).ok().map(|t| t.to_string_lossy().into_owned())
+    } else {
+        None
+    };
+
+    // Unfollowed links pass for working ones, since nothing shown could tell
+    let ftype = if is_link && !follow {
+        FileType::Symlink
+    } else {
+        get_file_type(&stat_res, link_stat.as_ref())
+    };
+    let followed = if is_link { link_stat.as_ref() } else { Some(&stat_res) };
+    // Regular files are only opened when their contents matter; until then they stay Unknown
+    let content_type = match followed {
+        _ if is_link && !follow => ContentType::Unknown,
+        Some(s) if !needs.content && s.st_mode & libc::S_IFMT == libc::S_IFREG => {
+            ContentType::Unknown
+        }
+        _ => get_content_type(path, followed),
+    };
     let time_epoch = stat_res.st_mtime.to_string();
 
     Some(FileRowInfo {
@@ -79,6 +229,8 @@ pub fn get_row_info(fname: &str) -> Option<FileRowInfo> {
         stat_res,
         content_type,
         time_epoch,
+        link_stat,
+        link_target,
     })
 }
 
@@ -86,8 +238,11 @@ pub fn get_row_info(fname: &str) -> Option<FileRowInfo> {
 mod tests {
     use super::*;
     use tempfile::TempDir;
+    use std::time::UNIX_EPOCH;
     use std::fs::File;
     use std::io::Write;
+    use std::os::unix::fs::symlink;
+    use std::os::unix::net::UnixListener;
 
     #[test]
     fn test_get_stat_result_valid_file() {
@@ -117,28 +272,137 @@ mod tests {
         assert!(result.st_size > 0);
     }
 
+    #[test]
+    fn test_get_stat_result_has_times() {
+        let dir = TempDir::new().unwrap();
+        let file_path = dir.path().join("test.txt");
+        File::create(&file_path).unwrap();
+
+        let result = get_stat_result(&file_path).unwrap();
+        assert!(result.st_mtime > 0);
+        assert!(result.st_atime > 0);
+        assert!(result.st_ctime > 0);
+        assert!((0..1_000_000_000).contains(&result.st_mtime_nsec));
+    }
+
+    // std reads birth times on glibc, so it tells whether this filesystem keeps them
+    #[test]
+    fn test_get_stat_result_has_btime() {
+        let dir = TempDir::new().unwrap();
+        let file_path = dir.path().join("test.txt");
+        File::create(&file_path).unwrap();
+
+        let result = get_stat_result(&file_path).unwrap();
+        let created = fs::metadata(&file_path).unwrap().created();
+        if let Ok(created) = created {
+            let secs = created.duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+            assert_eq!(result.st_btime, Some(secs));
+        }
+    }
+
+    #[test]
+    fn test_get_stat_result_falls_back_without_statx() {
+        let dir = TempDir::new().unwrap();
+        let file_path = dir.path().join("test.txt");
+        fs::write(&file_path, "hello").unwrap();
+        let link_path = dir.path().join("link");
+        symlink(&file_path, &link_path).unwrap();
+        let full = get_stat_result(&file_path).unwrap();
+
+        for errno in [libc::ENOSYS, libc::EPERM] {
+            let refused = || Err(io::Error::from_raw_os_error(errno));
+            let result = or_fallback_stat(refused(), &file_path, false).unwrap();
+            assert_eq!(result.st_size, 5);
+            assert_eq!(result.st_mode, full.st_mode);
+            assert_eq!(result.st_mtime, full.st_mtime);
+            assert_eq!(result.st_btime, None);
+
+            let link = or_fallback_stat(refused(), &link_path, false).unwrap();
+            assert!(is_symlink(&link));
+            let followed = or_fallback_stat(refused(), &link_path, true).unwrap();
+            assert_eq!(followed.st_size, 5);
+        }
+
+        let missing = Err(io::Error::from_raw_os_error(libc::ENOENT));
+        assert!(or_fallback_stat(missing, &file_path, false).is_none());
+    }
+
     #[test]
     fn test_get_file_type_file() {
         let dir = TempDir::new().unwrap();
         let file_path = dir.path().join("test.txt");
         File::create(&file_path).unwrap();
 
-        let result = get_file_type(&file_path);
+        let stat_res = get_stat_result(&file_path).unwrap();
+        let result = get_file_type(&stat_res, None);
         assert_eq!(result, FileType::File);
     }
 
     #[test]
     fn test_get_file_type_directory() {
         let dir = TempDir::new().unwrap();
-        let result = get_file_type(dir.path());
+        let stat_res = get_stat_result(dir.path()).unwrap();
+        let result = get_file_type(&stat_res, None);
         assert_eq!(result, FileType::Directory);
     }
 
+    #[test]
+    fn test_get_file_type_symlink() {
+        let dir = TempDir::new().unwrap();
+        let file_path = dir.path().join("target.txt");
+        let link_path = dir.path().join("link");
+        File::create(&file_path).unwrap();
+        symlink(&file_path, &link_path).unwrap();
+
+        let stat_res = get_stat_result(&link_path).unwrap();
+        let target = get_stat_result(&file_path);
+        assert_eq!(get_file_type(&stat_res, target.as_ref()), FileType::Symlink);
+    }
+
+    #[test]
+    fn test_get_file_type_broken_symlink() {
+        let dir = TempDir::new().unwrap();
+        let link_path = dir.path().join("dangling");
+        symlink(dir.path().join("missing"), &link_path).unwrap();
+
+        let stat_res = get_stat_result(&link_path).unwrap();
+        assert_eq!(get_file_type(&stat_res, None), FileType::BrokenSymlink);
+    }
+
+    #[test]
+    fn test_get_file_type_fifo() {
+        let dir = TempDir::new().unwrap();
+        let fifo_path = dir.path().join("pipe");
+        let c_path = CString::new(fifo_path.to_str().unwrap()).unwrap();
+        assert_eq!(unsafe { libc::mkfifo(c_path.as_ptr(), 0o644) }, 0);
+
+        let stat_res = get_stat_result(&fifo_path).unwrap();
+        assert_eq!(get_file_type(&stat_res, None), FileType::Fifo);
+        assert_eq!(get_content_type(&fifo_path, Some(&stat_res)), ContentType::Other);
+    }
+
+    #[test]
+    fn test_get_file_type_socket() {
+        let dir = TempDir::new().unwrap();
+        let sock_path = dir.path().join("sock");
+        let _listener = UnixListener::bind(&sock_path).unwrap();
+
+        let stat_res = get_stat_result(&sock_path).unwrap();
+        assert_eq!(get_file_type(&stat_res, None), FileType::Socket);
+    }
+
+    #[test]
+    fn test_get_file_type_char_device() {
+        let path = Path::new("/dev/null");
+        let stat_res = get_stat_result(path).unwrap();
+        assert_eq!(get_file_type(&stat_res, None), FileType::CharDevice);
+    }
+
     #[test]
     fn test_get_content_type_directory() {
         let dir = TempDir::new().unwrap();
-        let stat_res = get_stat_result(dir.path()).unwrap();
-        let result = get_content_type(dir.path(), &stat_res);
+        let stat_res = get_stat_result(dir.path());
+        let result = get_content_type(dir.path(), stat_res.as_ref());
         assert_eq!(result, ContentType::Directory);
     }
 
@@ -148,8 +412,8 @@ mod tests {
         let file_path = dir.path().join("empty.txt");
         File::create(&file_path).unwrap();
 
-        let stat_res = get_stat_result(&file_path).unwrap();
-        let result = get_content_type(&file_path, &stat_res);
+        let stat_res = get_stat_result(&file_path);
+        let result = get_content_type(&file_path, stat_res.as_ref());
         assert_eq!(result, ContentType::Empty);
     }
 
@@ -160,8 +424,8 @@ mod tests {
         let mut file = File::create(&file_path).unwrap();
         writeln!(file, "Hello, World!").unwrap();
 
-        let stat_res = get_stat_result(&file_path).unwrap();
-        let result = get_content_type(&file_path, &stat_res);
+        let stat_res = get_stat_result(&file_path);
+        let result = get_content_type(&file_path, stat_res.as_ref());
         assert_eq!(result, ContentType::Text);
     }
 
@@ -172,7 +436,7 @@ mod tests {
         let mut file = File::create(&file_path).unwrap();
         writeln!(file, "Hello").unwrap();
 
-        let result = get_row_info(file_path.to_str().unwrap());
+        let result = get_row_info(file_path.to_str().unwrap(), InfoNeeds::ALL, None);
         assert!(result.is_some());
 
         let info = result.unwrap();
@@ -183,7 +447,7 @@ mod tests {
     #[test]
     fn test_get_row_info_directory() {
         let dir = TempDir::new().unwrap();
-        let result = get_row_info(dir.path().to_str().unwrap());
+        let result = get_row_info(dir.path().to_str().unwrap(), InfoNeeds::ALL, None);
         assert!(result.is_some());
 
         let info = result.unwrap();
@@ -193,7 +457,112 @@ mod tests {
 
     #[test]
     fn test_get_row_info_nonexistent() {
-        let result = get_row_info("/nonexistent/path/12345.txt");
+        let result = get_row_info("/nonexistent/path/12345.txt", InfoNeeds::ALL, None);
         assert!(result.is_none());
     }
+
+    #[test]
+    fn test_get_content_type_missing_target() {
+        assert_eq!(get_content_type(Path::new("/nonexistent"), None), ContentType::NotReadable);
+    }
+
+    #[test]
+    fn test_get_row_info_symlink_reads_target_once() {
+        let dir = TempDir::new().unwrap();
+        let sub = dir.path().join("sub");
+        fs::create_dir(&sub).unwrap();
+        let link_path = dir.path().join("link");
+        symlink("sub", &link_path).unwrap();
+
+        let info = get_row_info(link_path.to_str().unwrap(), InfoNeeds::ALL, None).unwrap();
+        assert_eq!(info.ftype, FileType::Symlink);
+        assert_eq!(info.content_type, ContentType::Directory);
+        assert_eq!(info.link_target.as_deref(), Some("sub"));
+        assert_eq!(info.link_stat.unwrap().st_mode & libc::S_IFMT, libc::S_IFDIR);
+    }
+
+    #[test]
+    fn test_get_row_info_broken_symlink() {
+        let dir = TempDir::new().unwrap();
+        let link_path = dir.path().join("dangling");
+        symlink("missing", &link_path).unwrap();
+
+        let info = get_row_info(link_path.to_str().unwrap(), InfoNeeds::ALL, None).unwrap();
+        assert_eq!(info.ftype, FileType::BrokenSymlink);
+        assert_eq!(info.content_type, ContentType::NotReadable);
+        assert_eq!(info.link_target.as_deref(), Some("missing"));
+        assert!(info.link_stat.is_none());
+    }
+
+    #[test]
+    fn test_get_row_info_plain_file_has_no_link() {
+        let dir = TempDir::new().unwrap();
+        let file_path = dir.path().join("plain.txt");
+        File::create(&file_path).unwrap();
+
+        let info = get_row_info(file_path.to_str().unwrap(), InfoNeeds::ALL, None).unwrap();
+        assert!(info.link_stat.is_none());
+        assert!(info.link_target.is_none());
+    }
+
+    #[test]
+    fn test_get_row_info_names_only_skips_readlink_and_sniff() {
+        let dir = TempDir::new().unwrap();
+        let file_path = dir.path().join("text.txt");
+        fs::write(&file_path, "Hello\n").unwrap();
+        let link_path = dir.path().join("link");
+        symlink("text.txt", &link_path).unwrap();
+
+        let needs = InfoNeeds::default();
+        let entry_type = fs::symlink_metadata(&file_path).unwrap().file_type();
+        let info = get_row_info(file_path.to_str().unwrap(), needs, Some(entry_type)).unwrap();
+        assert_eq!(info.ftype, FileType::File);
+        assert_eq!(info.content_type, ContentType::Unknown);
+        assert_eq!(info.stat_res.st_size, 0);
+
+        let entry_type = fs::symlink_metadata(&link_path).unwrap().file_type();
+        let info = get_row_info(link_path.to_str().unwrap(), needs, Some(entry_type)).unwrap();
+        assert_eq!(info.ftype, FileType::Symlink);
+        assert_eq!(info.content_type, ContentType::Unknown);
+        assert!(info.link_target.is_none());
+        assert!(info.link_stat.is_none());
+    }
+
+    #[test]
+    fn test_get_row_info_follows_links_when_needed() {
+        let dir = TempDir::new().unwrap();
+        let link_path = dir.path().join("dangling");
+        symlink("missing", &link_path).unwrap();
+        let entry_type = fs::symlink_metadata(&link_path).unwrap().file_type();
+        let fname = link_path.to_str().unwrap();
+
+        let needs = InfoNeeds {
+            link_stat: true,
+            ..InfoNeeds::default()
+        };
+        let info = get_row_info(fname, needs, Some(entry_type)).unwrap();
+        assert_eq!(info.ftype, FileType::BrokenSymlink);
+        assert!(info.link_target.is_none());
+    }
+
+    #[test]
+    fn test_get_row_info_stat_without_entry_type() {
+        let dir = TempDir::new().unwrap();
+        let file_path = dir.path().join("text.txt");
+        fs::write(&file_path, "Hello\n").unwrap();
+
+        let info = get_row_info(file_path.to_str().unwrap(), InfoNeeds::default(), None).unwrap();
+        assert_eq!(info.stat_res.st_size, 6);
+        assert_eq!(info.content_type, ContentType::Unknown);
+    }
+
+    #[test]
+    fn test_get_row_info_dir_entry_type() {
+        let dir = TempDir::new().unwrap();
+        let entry_type = fs::symlink_metadata(dir.path()).unwrap().file_type();
+        let fname = dir.path().to_str().unwrap();
+        let info = get_row_info(fname, InfoNeeds::default(), Some(entry_type)).unwrap();
+        assert_eq!(info.ftype, FileType::Directory);
+        assert_eq!(info.content_type, ContentType::Directory);
+    }
 }
@@ -0,0 +1,681 @@
+use regex_lite::{Regex, RegexBuilder};
+
+use crate::permissions::UserGroupCache;
+use crate::types::{Args, ContentType, FileRowInfo, FileType};
+
+pub const TYPE_FILTER_CHARS: &str = "dfletb";
+
+// `ELS_HIDE` holds a colon-separated list, like PATH
+pub fn parse_hide_list(spec: &str) -> Vec<String> {
+    spec.split(':')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pat: Vec<char> = pattern.chars().collect();
+    let txt: Vec<char> = text.chars().collect();
+
+    let mut p = 0;
+    let mut t = 0;
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < txt.len() {
+        if p < pat.len() {
+            match pat[p] {
+                '*' => {
+                    star = Some((p, t));
+                    p += 1;
+                    continue;
+                }
+                '?' => {
+                    p += 1;
+                    t += 1;
+                    continue;
+                }
+                '[' => {
+                    if let Some((matched, next)) = match_class(&pat, p, txt[t]) {
+                        if matched {
+                            p = next;
+                            t += 1;
+                            continue;
+                        }
+                    } else if txt[t] == '[' {
+                        p += 1;
+                        t += 1;
+                        continue;
+                    }
+                }
+                '\\' if p + 1 < pat.len() => {
+                    if pat[p + 1] == txt[t] {
+                        p += 2;
+                        t += 1;
+                        continue;
+                    }
+                }
+                c => {
+                    if c == txt[t] {
+                        p += 1;
+                        t += 1;
+                        continue;
+                    }
+                }
+            }
+        }
+
+        // Mismatch: let the last `*` swallow one more character, if there was one
+        match star {
+            Some((star_p, star_t)) => {
+                p = star_p + 1;
+                t = star_t + 1;
+                star = Some((star_p, star_t + 1));
+            }
+            None => return false,
+        }
+    }
+
+    pat[p..].iter().all(|&c| c == '*')
+}
+
+// Returns whether `c` is in the class opening at `pat[start]`, and the index after it
+fn match_class(pat: &[char], start: usize, c: char) -> Option<(bool, usize)> {
+    let mut i = start + 1;
+    let negated = i < pat.len() && (pat[i] == '!' || pat[i] == '^');
+    if negated {
+        i += 1;
+    }
+
+    let mut matched = false;
+    let mut first = true;
+
+    while i < pat.len() {
+        if pat[i] == ']' && !first {
+            return Some((matched != negated, i + 1));
+        }
+
+        let lo = pat[i];
+        if i + 2 < pat.len() && pat[i + 1] == '-' && pat[i + 2] != ']' {
+            if lo <= c && c <= pat[i + 2] {
+                matched = true;
+            }
+            i += 3;
+        } else {
+            if lo == c {
+                matched = true;
+            }
+            i += 1;
+        }
+        first = false;
+    }
+
+    None
+}
+
+fn build_regexes(patterns: &[String], case_sensitive: bool) -> Result<Vec<Regex>, String> {
+    patterns
+        .iter()
+        .map(|p| {
+            RegexBuilder::new(p)
+                .case_insensitive(!case_sensitive)
+                .build()
+                .map_err(|e| format!("invalid regex '{}': {}", p, e))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct NameFilter {
+    substring: Option<String>,
+    globs: Vec<String>,
+    regexes: Vec<Regex>,
+    exclude_globs: Vec<String>,
+    exclude_regexes: Vec<Regex>,
+    case_sensitive: bool,
+    hide: Vec<String>,
+    hide_dotfiles: bool,
+}
+
+impl NameFilter {
+    pub fn new(args: &Args) -> Result<Self, String> {
+        let fold = |s: &String| {
+            if args.case_sensitive {
+                s.clone()
+            } else {
+                s.to_lowercase()
+            }
+        };
+
+        Ok(Self {
+            substring: args.filter.as_ref().map(fold),
+            globs: args.globs.iter().map(fold).collect(),
+            regexes: build_regexes(&args.regexes, args.case_sensitive)?,
+            exclude_globs: args.exclude_globs.iter().map(fold).collect(),
+            exclude_regexes: build_regexes(&args.exclude_regexes, args.case_sensitive)?,
+            case_sensitive: args.case_sensitive,
+            hide: args.hide.clone(),
+            hide_dotfiles: args.hide_dotfiles,
+        })
+    }
+
+    // Hide rules only apply inside listed directories; paths named on the command line always show
+    pub fn is_hidden(&self, name: &str) -> bool {
+        (self.hide_dotfiles && name.starts_with('.'))
+            || self.hide.iter().any(|p| glob_match(p, name))
+    }
+
+    // Each kind of include pattern must match, any one pattern within a kind is enough
+    pub fn matches(&self, name: &str) -> bool {
+        let folded = if self.case_sensitive {
+            name.to_string()
+        } else {
+            name.to_lowercase()
+        };
+
+        if let Some(sub) = &self.substring {
+            if !folded.contains(sub.as_str()) {
+                return false;
+            }
+        }
+
+        if !self.globs.is_empty() && !self.globs.iter().any(|g| glob_match(g, &folded)) {
+            return false;
+        }
+
+        if !self.regexes.is_empty() && !self.regexes.iter().any(|r| r.is_match(name)) {
+            return false;
+        }
+
+        if self.exclude_globs.iter().any(|g| glob_match(g, &folded)) {
+            return false;
+        }
+
+        !self.exclude_regexes.iter().any(|r| r.is_match(name))
+    }
+}
+
+fn split_number(s: &str) -> Result<(f64, String), String> {
+    let s = s.trim();
+    let idx = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (num, unit) = s.split_at(idx);
+
+    let value: f64 = num
+        .parse()
+        .map_err(|_| format!("invalid number in '{}'", s))?;
+
+    Ok((value, unit.to_string()))
+}
+
+// Suffixes are binary multiples, as with `find -size`: 10K is 10240 bytes
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let (value, unit) = split_number(s)?;
+    let unit = unit.to_lowercase();
+    // At most one `b` or `ib` suffix, and `ib` only after a unit letter
+    let unit = unit
+        .strip_suffix("ib")
+        .filter(|u| !u.is_empty())
+        .or_else(|| unit.strip_suffix('b'))
+        .unwrap_or(&unit);
+
+    let multiplier: u64 = match unit {
+        "" => 1,
+        "k" => 1 << 10,
+        "m" => 1 << 20,
+        "g" => 1 << 30,
+        "t" => 1 << 40,
+        "p" => 1 << 50,
+        _ => return Err(format!("unknown size unit in '{}'", s)),
+    };
+
+    Ok((value * multiplier as f64) as u64)
+}
+
+pub fn parse_age(s: &str) -> Result<i64, String> {
+    let (value, unit) = split_number(s)?;
+
+    let multiplier: i64 = match unit.as_str() {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        "y" => 365 * 24 * 60 * 60,
+        _ => return Err(format!("unknown age unit in '{}'", s)),
+    };
+
+    Ok((value * multiplier as f64) as i64)
+}
+
+pub fn parse_type_filter(s: &str) -> Result<Vec<char>, String> {
+    let types: Vec<char> = s.chars().filter(|&c| c != ',').collect();
+
+    if types.is_empty() {
+        return Err(String::from("no file types given"));
+    }
+
+    match types.iter().find(|&&c| !TYPE_FILTER_CHARS.contains(c)) {
+        Some(c) => Err(format!("unknown file type '{}'", c)),
+        None => Ok(types),
+    }
+}
+
+fn matches_type(info: &FileRowInfo, type_char: char) -> bool {
+    match type_char {
+        'd' => info.is_dir(),
+        'f' => info.ftype == FileType::File,
+        'l' => matches!(info.ftype, FileType::Symlink | FileType::BrokenSymlink),
+        'e' => info.content_type == ContentType::BinaryExecutable,
+        't' => info.content_type == ContentType::Text,
+        'b' => info.content_type == ContentType::BinaryOther,
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RowFilter {
+    larger_than: Option<u64>,
+    smaller_than: Option<u64>,
+    newer_than: Option<i64>,
+    older_than: Option<i64>,
+    owner: Option<String>,
+    types: Vec<char>,
+    broken_links: bool,
+}
+
+impl RowFilter {
+    pub fn new(args: &Args, now: i64) -> Self {
+        Self {
+            larger_than: args.larger_than,
+            smaller_than: args.smaller_than,
+            newer_than: args.newer_than.map(|age| now - age),
+            older_than: args.older_than.map(|age| now - age),
+            owner: args.owner.clone(),
+            types: args.types.clone(),
+            broken_links: args.broken_links,
+        }
+    }
+
+    pub fn matches(&self, info: &FileRowInfo, cache: &UserGroupCache) -> bool {
+        let stat_res = &info.stat_res;
+
+        if self.broken_links && info.ftype != FileType::BrokenSymlink {
+            return false;
+        }
+
+        if self.larger_than.is_some_and(|min| stat_res.st_size <= min) {
+            return false;
+        }
+
+        if self.smaller_than.is_some_and(|max| stat_res.st_size >= max) {
+            return false;
+        }
+
+        if self.newer_than.is_some_and(|cutoff| stat_res.st_mtime < cutoff) {
+            return false;
+        }
+
+        if self.older_than.is_some_and(|cutoff| stat_res.st_mtime >= cutoff) {
+            return false;
+        }
+
+        if let Some(owner) = &self.owner {
+            let uid = stat_res.st_uid;
+            if cache.get_user_name(uid) != *owner && uid.to_string() != *owner {
+                return false;
+            }
+        }
+
+        self.types.is_empty() || self.types.iter().any(|&t| matches_type(info, t))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::fixtures;
+
+    fn make_filter(args: Args) -> NameFilter {
+        NameFilter::new(&args).unwrap()
+    }
+
+    #[test]
+    fn test_glob_match_literal() {
+        assert!(glob_match("file.txt", "file.txt"));
+        assert!(!glob_match("file.txt", "file.txz"));
+    }
+
+    #[test]
+    fn test_glob_match_star() {
+        assert!(glob_match("*.rs", "main.rs"));
+        assert!(glob_match("*.rs", ".rs"));
+        assert!(!glob_match("*.rs", "main.rst"));
+        assert!(glob_match("a*b*c", "axxbyyc"));
+        assert!(glob_match("*", ""));
+    }
+
+    #[test]
+    fn test_glob_match_question() {
+        assert!(glob_match("?.log", "a.log"));
+        assert!(!glob_match("?.log", "ab.log"));
+    }
+
+    #[test]
+    fn test_glob_match_class() {
+        assert!(glob_match("[abc].txt", "b.txt"));
+        assert!(!glob_match("[abc].txt", "d.txt"));
+        assert!(glob_match("file[0-9]", "file7"));
+        assert!(glob_match("file[!0-9]", "filex"));
+        assert!(!glob_match("file[!0-9]", "file1"));
+    }
+
+    #[test]
+    fn test_glob_match_escape() {
+        assert!(glob_match("\\*.txt", "*.txt"));
+        assert!(!glob_match("\\*.txt", "a.txt"));
+    }
+
+    #[test]
+    fn test_glob_match_unclosed_class() {
+        assert!(glob_match("[abc", "[abc"));
+    }
+
+    #[test]
+    fn test_parse_hide_list() {
+        assert_eq!(
+            parse_hide_list(".DS_Store:__pycache__: *.pyc ::"),
+            vec![".DS_Store", "__pycache__", "*.pyc"]
+        );
+        assert!(parse_hide_list("").is_empty());
+    }
+
+    #[test]
+    fn test_name_filter_default_hides_nothing() {
+        let filter = NameFilter::default();
+        assert!(!filter.is_hidden(".bashrc"));
+    }
+
+    #[test]
+    fn test_name_filter_hide_dotfiles() {
+        let filter = make_filter(Args {
+            hide_dotfiles: true,
+            ..Args::default()
+        });
+        assert!(filter.is_hidden(".git"));
+        assert!(!filter.is_hidden("src"));
+    }
+
+    #[test]
+    fn test_name_filter_hide_patterns() {
+        let filter = make_filter(Args {
+            hide: vec![String::from("__pycache__"), String::from("*.pyc")],
+            ..Args::default()
+        });
+        assert!(filter.is_hidden("__pycache__"));
+        assert!(filter.is_hidden("mod.pyc"));
+        assert!(!filter.is_hidden("mod.py"));
+        assert!(!filter.is_hidden(".env"));
+    }
+
+    #[test]
+    fn test_name_filter_default_matches_all() {
+        let filter = NameFilter::default();
+        assert!(filter.matches("anything"));
+    }
+
+    #[test]
+    fn test_name_filter_substring_case_insensitive() {
+        let filter = make_filter(Args {
+            filter: Some(String::from("Test")),
+            ..Args::default()
+        });
+        assert!(filter.matches("my_TEST.txt"));
+        assert!(!filter.matches("other.log"));
+    }
+
+    #[test]
+    fn test_name_filter_glob() {
+        let filter = make_filter(Args {
+            globs: vec![String::from("*.log")],
+            ..Args::default()
+        });
+        assert!(filter.matches("app.log"));
+        assert!(filter.matches("APP.LOG"));
+        assert!(!filter.matches("app.txt"));
+    }
+
+    #[test]
+    fn test_name_filter_multiple_globs_any() {
+        let filter = make_filter(Args {
+            globs: vec![String::from("*.rs"), String::from("*.toml")],
+            ..Args::default()
+        });
+        assert!(filter.matches("main.rs"));
+        assert!(filter.matches("Cargo.toml"));
+        assert!(!filter.matches("README.md"));
+    }
+
+    #[test]
+    fn test_name_filter_regex() {
+        let filter = make_filter(Args {
+            regexes: vec![String::from(r"^test_.*\.py$")],
+            ..Args::default()
+        });
+        assert!(filter.matches("test_main.py"));
+        assert!(!filter.matches("main_test.py"));
+    }
+
+    #[test]
+    fn test_name_filter_excludes() {
+        let filter = make_filter(Args {
+            exclude_globs: vec![String::from("*.pyc")],
+            exclude_regexes: vec![String::from("^~")],
+            ..Args::default()
+        });
+        assert!(filter.matches("main.py"));
+        assert!(!filter.matches("main.pyc"));
+        assert!(!filter.matches("~backup"));
+    }
+
+    #[test]
+    fn test_name_filter_case_sensitive() {
+        let filter = make_filter(Args {
+            globs: vec![String::from("*.LOG")],
+            regexes: vec![String::from("^App")],
+            case_sensitive: true,
+            ..Args::default()
+        });
+        assert!(filter.matches("App.LOG"));
+        assert!(!filter.matches("App.log"));
+        assert!(!filter.matches("app.LOG"));
+    }
+
+    #[test]
+    fn test_name_filter_invalid_regex() {
+        let args = Args {
+            regexes: vec![String::from("(unclosed")],
+            ..Args::default()
+        };
+        assert!(NameFilter::new(&args).is_err());
+    }
+
+    // Ids no account has, so owner filters fall back to matching numbers
+    fn make_test_info(ftype: FileType, content_type: ContentType) -> FileRowInfo {
+        let mut info = fixtures::make_test_info("test", ftype, content_type);
+        info.stat_res.st_uid = 99999;
+        info.stat_res.st_gid = 99999;
+        info
+    }
+
+    fn make_row_filter(args: Args) -> RowFilter {
+        RowFilter::new(&args, 1704067200)
+    }
+
+    #[test]
+    fn test_parse_size_plain() {
+        assert_eq!(parse_size("512"), Ok(512));
+    }
+
+    #[test]
+    fn test_parse_size_suffixes() {
+        assert_eq!(parse_size("10K"), Ok(10 * 1024));
+        assert_eq!(parse_size("10M"), Ok(10 * 1024 * 1024));
+        assert_eq!(parse_size("1g"), Ok(1024 * 1024 * 1024));
+        assert_eq!(parse_size("2MB"), Ok(2 * 1024 * 1024));
+        assert_eq!(parse_size("2MiB"), Ok(2 * 1024 * 1024));
+        assert_eq!(parse_size("1.5K"), Ok(1536));
+    }
+
+    #[test]
+    fn test_parse_size_invalid() {
+        assert!(parse_size("10X").is_err());
+        assert!(parse_size("M").is_err());
+    }
+
+    #[test]
+    fn test_parse_size_single_byte_suffix() {
+        assert_eq!(parse_size("10b"), Ok(10));
+        assert!(parse_size("10bb").is_err());
+        assert!(parse_size("10kbbb").is_err());
+        assert!(parse_size("10ib").is_err());
+        assert!(parse_size("10kibb").is_err());
+    }
+
+    #[test]
+    fn test_parse_age_units() {
+        assert_eq!(parse_age("30"), Ok(30));
+        assert_eq!(parse_age("5m"), Ok(300));
+        assert_eq!(parse_age("2h"), Ok(7200));
+        assert_eq!(parse_age("2d"), Ok(172800));
+        assert_eq!(parse_age("1w"), Ok(604800));
+        assert_eq!(parse_age("1y"), Ok(31536000));
+    }
+
+    #[test]
+    fn test_parse_age_invalid() {
+        assert!(parse_age("2x").is_err());
+        assert!(parse_age("").is_err());
+    }
+
+    #[test]
+    fn test_parse_type_filter() {
+        assert_eq!(parse_type_filter("d"), Ok(vec!['d']));
+        assert_eq!(parse_type_filter("d,f"), Ok(vec!['d', 'f']));
+        assert!(parse_type_filter("z").is_err());
+        assert!(parse_type_filter("").is_err());
+    }
+
+    #[test]
+    fn test_row_filter_default_matches_all() {
+        let info = make_test_info(FileType::File, ContentType::Text);
+        assert!(RowFilter::default().matches(&info, &UserGroupCache::new()));
+    }
+
+    #[test]
+    fn test_row_filter_size() {
+        let info = make_test_info(FileType::File, ContentType::Text);
+        let cache = UserGroupCache::new();
+        let larger = |n| {
+            make_row_filter(Args {
+                larger_than: Some(n),
+                ..Args::default()
+            })
+        };
+        let smaller = |n| {
+            make_row_filter(Args {
+                smaller_than: Some(n),
+                ..Args::default()
+            })
+        };
+        assert!(larger(1000).matches(&info, &cache));
+        assert!(!larger(1024).matches(&info, &cache));
+        assert!(smaller(2000).matches(&info, &cache));
+        assert!(!smaller(1024).matches(&info, &cache));
+    }
+
+    #[test]
+    fn test_row_filter_age() {
+        let mut info = make_test_info(FileType::File, ContentType::Text);
+        let cache = UserGroupCache::new();
+        let newer = make_row_filter(Args {
+            newer_than: Some(3600),
+            ..Args::default()
+        });
+        let older = make_row_filter(Args {
+            older_than: Some(3600),
+            ..Args::default()
+        });
+
+        info.stat_res.st_mtime = 1704067200 - 60;
+        assert!(newer.matches(&info, &cache));
+        assert!(!older.matches(&info, &cache));
+
+        info.stat_res.st_mtime = 1704067200 - 7200;
+        assert!(!newer.matches(&info, &cache));
+        assert!(older.matches(&info, &cache));
+    }
+
+    #[test]
+    fn test_row_filter_owner_by_uid() {
+        let info = make_test_info(FileType::File, ContentType::Text);
+        let cache = UserGroupCache::new();
+        let matching = make_row_filter(Args {
+            owner: Some(String::from("99999")),
+            ..Args::default()
+        });
+        let other = make_row_filter(Args {
+            owner: Some(String::from("nobody-here")),
+            ..Args::default()
+        });
+        assert!(matching.matches(&info, &cache));
+        assert!(!other.matches(&info, &cache));
+    }
+
+    #[test]
+    fn test_row_filter_types() {
+        let cache = UserGroupCache::new();
+        let filter = make_row_filter(Args {
+            types: vec!['d', 'l'],
+            ..Args::default()
+        });
+        let dir = make_test_info(FileType::Directory, ContentType::Directory);
+        let link = make_test_info(FileType::Symlink, ContentType::Text);
+        let text = make_test_info(FileType::File, ContentType::Text);
+        assert!(filter.matches(&dir, &cache));
+        assert!(filter.matches(&link, &cache));
+        assert!(!filter.matches(&text, &cache));
+    }
+
+    #[test]
+    fn test_row_filter_content_types() {
+        let cache = UserGroupCache::new();
+        let exe = make_test_info(FileType::File, ContentType::BinaryExecutable);
+        let bin = make_test_info(FileType::File, ContentType::BinaryOther);
+        let only = |c| {
+            make_row_filter(Args {
+                types: vec![c],
+                ..Args::default()
+            })
+        };
+        assert!(only('e').matches(&exe, &cache));
+        assert!(!only('e').matches(&bin, &cache));
+        assert!(only('b').matches(&bin, &cache));
+        assert!(only('f').matches(&bin, &cache));
+        assert!(!only('t').matches(&bin, &cache));
+    }
+
+    #[test]
+    fn test_row_filter_broken_links() {
+        let cache = UserGroupCache::new();
+        let filter = make_row_filter(Args {
+            broken_links: true,
+            ..Args::default()
+        });
+        let broken = make_test_info(FileType::BrokenSymlink, ContentType::NotReadable);
+        let alive = make_test_info(FileType::Symlink, ContentType::Text);
+        assert!(filter.matches(&broken, &cache));
+        assert!(!filter.matches(&alive, &cache));
+    }
+}
@@ -0,0 +1,917 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+
+use miniz_oxide::inflate::decompress_to_vec_zlib_with_limit;
+
+pub type ObjectId = [u8; 20];
+
+pub const MODE_GITLINK: u32 = 0o160000;
+pub const MODE_SYMLINK: u32 = 0o120000;
+const MODE_TREE: u32 = 0o40000;
+
+const INDEX_SIGNATURE: &[u8] = b"DIRC";
+const INDEX_ENTRY_HEADER: usize = 62;
+const INDEX_EXTENDED: u16 = 0x4000;
+const INDEX_SKIP_WORKTREE: u16 = 0x4000;
+// A split index keeps most entries in a shared file and a sparse one folds whole
+// directories into one entry; either way the entries here are not the full list
+const INDEX_PARTIAL_EXTENSIONS: [&[u8]; 2] = [b"link", b"sdir"];
+const INDEX_CHECKSUM: usize = 20;
+
+const PACK_IDX_SIGNATURE: &[u8] = b"\xfftOc";
+const PACK_OFS_DELTA: u8 = 6;
+const PACK_REF_DELTA: u8 = 7;
+const MAX_DELTA_CHAIN: usize = 64;
+const MAX_CACHED_BASES: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectKind {
+    Commit,
+    Tree,
+    Blob,
+    Tag,
+}
+
+impl ObjectKind {
+    fn from_name(name: &[u8]) -> Option<Self> {
+        match name {
+            b"commit" => Some(ObjectKind::Commit),
+            b"tree" => Some(ObjectKind::Tree),
+            b"blob" => Some(ObjectKind::Blob),
+            b"tag" => Some(ObjectKind::Tag),
+            _ => None,
+        }
+    }
+
+    fn from_pack_type(kind: u8) -> Option<Self> {
+        match kind {
+            1 => Some(ObjectKind::Commit),
+            2 => Some(ObjectKind::Tree),
+            3 => Some(ObjectKind::Blob),
+            4 => Some(ObjectKind::Tag),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexEntry {
+    pub mtime: (u32, u32),
+    pub size: u32,
+    pub mode: u32,
+    pub id: ObjectId,
+    pub stage: u8,
+    pub skip_worktree: bool,
+}
+
+pub fn parse_hex_id(hex: &str) -> Option<ObjectId> {
+    let hex = hex.trim();
+    if hex.len() != 40 {
+        return None;
+    }
+    let mut id = [0u8; 20];
+    for (i, byte) in id.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(id)
+}
+
+pub fn to_hex(id: &ObjectId) -> String {
+    id.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// What `git hash-object` would give the content, without writing anything
+pub fn hash_blob(content: &[u8]) -> ObjectId {
+    let mut hasher = sha1_smol::Sha1::new();
+    hasher.update(format!("blob {}\0", content.len()).as_bytes());
+    hasher.update(content);
+    hasher.digest().bytes()
+}
+
+// `.git` is a directory, or a file pointing elsewhere for worktrees and submodules
+pub fn find_repo_root(start: &Path) -> Option<PathBuf> {
+    let start = fs::canonicalize(start).ok()?;
+    start
+        .ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+pub fn resolve_git_dir(root: &Path) -> Option<PathBuf> {
+    let dot_git = root.join(".git");
+    if dot_git.is_dir() {
+        return Some(dot_git);
+    }
+
+    let content = fs::read_to_string(&dot_git).ok()?;
+    let target = content.trim().strip_prefix("gitdir:")?.trim();
+    Some(root.join(target))
+}
+
+// Linked worktrees keep HEAD and the index to themselves but share refs and objects
+pub fn get_common_dir(git_dir: &Path) -> PathBuf {
+    match fs::read_to_string(git_dir.join("commondir")) {
+        Ok(content) => git_dir.join(content.trim()),
+        Err(_) => git_dir.to_path_buf(),
+    }
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Option<u32> {
+    let bytes = data.get(pos..pos + 4)?;
+    Some(u32::from_be_bytes(bytes.try_into().ok()?))
+}
+
+fn read_u16(data: &[u8], pos: usize) -> Option<u16> {
+    let bytes = data.get(pos..pos + 2)?;
+    Some(u16::from_be_bytes(bytes.try_into().ok()?))
+}
+
+fn read_id(data: &[u8], pos: usize) -> Option<ObjectId> {
+    data.get(pos..pos + 20)?.try_into().ok()
+}
+
+// Index v4 prefix lengths use git's offset varint, where each continuation adds one
+fn read_offset_varint(data: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut byte = *data.get(*pos)?;
+    *pos += 1;
+    let mut value = (byte & 0x7f) as u64;
+    while byte & 0x80 != 0 {
+        byte = *data.get(*pos)?;
+        *pos += 1;
+        value = ((value + 1) << 7) | (byte & 0x7f) as u64;
+    }
+    Some(value)
+}
+
+// Versions 2 to 4; extensions after the entries are skipped, but None for split or sparse
+// indexes since their entries alone would leave most tracked files looking untracked
+pub fn parse_index(data: &[u8]) -> Option<Vec<(String, IndexEntry)>> {
+    if data.get(0..4)? != INDEX_SIGNATURE {
+        return None;
+    }
+    let version = read_u32(data, 4)?;
+    if !(2..=4).contains(&version) {
+        return None;
+    }
+    let count = read_u32(data, 8)? as usize;
+
+    // The header count is untrusted; every entry needs at least its fixed header
+    let mut entries = Vec::with_capacity(count.min(data.len() / INDEX_ENTRY_HEADER));
+    let mut pos = 12;
+    let mut prev_name: Vec<u8> = Vec::new();
+
+    for _ in 0..count {
+        let start = pos;
+        let mtime = (read_u32(data, pos + 8)?, read_u32(data, pos + 12)?);
+        let mode = read_u32(data, pos + 24)?;
+        let size = read_u32(data, pos + 36)?;
+        let id = read_id(data, pos + 40)?;
+        let flags = read_u16(data, pos + 60)?;
+        pos += INDEX_ENTRY_HEADER;
+
+        let mut skip_worktree = false;
+        if version >= 3 && flags & INDEX_EXTENDED != 0 {
+            skip_worktree = read_u16(data, pos)? & INDEX_SKIP_WORKTREE != 0;
+            pos += 2;
+        }
+
+        let name = if version == 4 {
+            let strip = read_offset_varint(data, &mut pos)? as usize;
+            let end = pos + data.get(pos..)?.iter().position(|&b| b == 0)?;
+            let keep = prev_name.len().checked_sub(strip)?;
+            let mut name = prev_name[..keep].to_vec();
+            name.extend_from_slice(&data[pos..end]);
+            pos = end + 1;
+            name
+        } else {
+            let end = pos + data.get(pos..)?.iter().position(|&b| b == 0)?;
+            let name = data[pos..end].to_vec();
+            // Entries are NUL-padded to a multiple of eight bytes
+            pos = start + (end - start + 8) / 8 * 8;
+            name
+        };
+
+        if mode == MODE_TREE {
+            return None;
+        }
+        let entry = IndexEntry {
+            mtime,
+            size,
+            mode,
+            id,
+            stage: ((flags >> 12) & 0x3) as u8,
+            skip_worktree,
+        };
+        entries.push((String::from_utf8_lossy(&name).into_owned(), entry));
+        prev_name = name;
+    }
+
+    while pos + 8 + INDEX_CHECKSUM <= data.len() {
+        let signature = &data[pos..pos + 4];
+        if INDEX_PARTIAL_EXTENSIONS.contains(&signature) {
+            return None;
+        }
+        pos += 8 + read_u32(data, pos + 4)? as usize;
+    }
+
+    Some(entries)
+}
+
+struct Pack {
+    idx: Vec<u8>,
+    data: File,
+    count: usize,
+    // Sorted entry offsets, so each entry's compressed length is the gap to the next
+    offsets: Vec<u64>,
+    data_end: u64,
+}
+
+impl Pack {
+    fn open(idx_path: &Path) -> Option<Self> {
+        let idx = fs::read(idx_path).ok()?;
+        if idx.get(0..4)? != PACK_IDX_SIGNATURE || read_u32(&idx, 4)? != 2 {
+            return None;
+        }
+        let count = read_u32(&idx, 8 + 255 * 4)? as usize;
+        let data = File::open(idx_path.with_extension("pack")).ok()?;
+        let data_end = data.metadata().ok()?.len().checked_sub(20)?;
+
+        let mut pack = Self {
+            idx,
+            data,
+            count,
+            offsets: Vec::new(),
+            data_end,
+        };
+        let mut offsets: Vec<u64> = (0..count).filter_map(|i| pack.get_offset(i)).collect();
+        offsets.sort_unstable();
+        pack.offsets = offsets;
+        Some(pack)
+    }
+
+    fn get_offset(&self, i: usize) -> Option<u64> {
+        let off32_base = 8 + 256 * 4 + self.count * 24;
+        let off = read_u32(&self.idx, off32_base + i * 4)?;
+        if off & 0x8000_0000 == 0 {
+            return Some(off as u64);
+        }
+        let off64_pos = off32_base + self.count * 4 + (off & 0x7fff_ffff) as usize * 8;
+        let bytes = self.idx.get(off64_pos..off64_pos + 8)?;
+        Some(u64::from_be_bytes(bytes.try_into().ok()?))
+    }
+
+    fn find(&self, id: &ObjectId) -> Option<u64> {
+        let fanout = |b: usize| read_u32(&self.idx, 8 + b * 4).map(|v| v as usize);
+        let first = id[0] as usize;
+        let mut lo = if first == 0 { 0 } else { fanout(first - 1)? };
+        let mut hi = fanout(first)?;
+        let ids_base = 8 + 256 * 4;
+
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            let candidate = self
+                .idx
+                .get(ids_base + mid * 20..ids_base + mid * 20 + 20)?;
+            match candidate.cmp(id.as_slice()) {
+                std::cmp::Ordering::Equal => return self.get_offset(mid),
+                std::cmp::Ordering::Less => lo = mid + 1,
+                std::cmp::Ordering::Greater => hi = mid,
+            }
+        }
+        None
+    }
+
+    fn read_entry(&self, offset: u64) -> Option<Vec<u8>> {
+        let next = match self.offsets.binary_search(&offset) {
+            Ok(i) => self.offsets.get(i + 1).copied().unwrap_or(self.data_end),
+            Err(_) => return None,
+        };
+        let mut buf = vec![0u8; next.checked_sub(offset)? as usize];
+        self.data.read_exact_at(&mut buf, offset).ok()?;
+        Some(buf)
+    }
+}
+
+// Applies a git delta: a source and target size, then copy-from-base and insert ops
+pub fn apply_delta(base: &[u8], delta: &[u8]) -> Option<Vec<u8>> {
+    let mut pos = 0;
+    let mut read_size = || {
+        let mut value = 0usize;
+        let mut shift = 0;
+        loop {
+            let byte = *delta.get(pos)?;
+            pos += 1;
+            value |= ((byte & 0x7f) as usize) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+    };
+
+    if read_size()? != base.len() {
+        return None;
+    }
+    let target_size = read_size()?;
+    let mut out = Vec::with_capacity(target_size);
+
+    while pos < delta.len() {
+        let op = delta[pos];
+        pos += 1;
+
+        if op & 0x80 != 0 {
+            let mut offset = 0usize;
+            let mut size = 0usize;
+            for i in 0..4 {
+                if op & (1 << i) != 0 {
+                    offset |= (*delta.get(pos)? as usize) << (i * 8);
+                    pos += 1;
+                }
+            }
+            for i in 0..3 {
+                if op & (0x10 << i) != 0 {
+                    size |= (*delta.get(pos)? as usize) << (i * 8);
+                    pos += 1;
+                }
+            }
+            if size == 0 {
+                size = 0x10000;
+            }
+            out.extend_from_slice(base.get(offset..offset.checked_add(size)?)?);
+        } else if op != 0 {
+            out.extend_from_slice(delta.get(pos..pos + op as usize)?);
+            pos += op as usize;
+        } else {
+            return None;
+        }
+    }
+
+    (out.len() == target_size).then_some(out)
+}
+
+pub struct ObjectStore {
+    objects_dir: PathBuf,
+    packs: Option<Vec<Pack>>,
+    // Delta bases keyed by pack and offset; trees in one commit share most of them
+    bases: HashMap<(usize, u64), (ObjectKind, Vec<u8>)>,
+}
+
+impl ObjectStore {
+    pub fn new(objects_dir: PathBuf) -> Self {
+        Self {
+            objects_dir,
+            packs: None,
+            bases: HashMap::new(),
+        }
+    }
+
+    fn load_packs(&mut self) {
+        if self.packs.is_some() {
+            return;
+        }
+        let pack_dir = self.objects_dir.join("pack");
+        let packs = fs::read_dir(pack_dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok())
+                    .map(|e| e.path())
+                    .filter(|p| p.extension().is_some_and(|ext| ext == "idx"))
+                    .filter_map(|p| Pack::open(&p))
+                    .collect()
+            })
+            .unwrap_or_default();
+        self.packs = Some(packs);
+    }
+
+    fn read_loose(&self, id: &ObjectId) -> Option<(ObjectKind, Vec<u8>)> {
+        let hex = to_hex(id);
+        let path = self.objects_dir.join(&hex[..2]).join(&hex[2..]);
+        let compressed = fs::read(path).ok()?;
+        let raw = decompress_to_vec_zlib_with_limit(&compressed, usize::MAX).ok()?;
+
+        let header_end = raw.iter().position(|&b| b == 0)?;
+        let kind_end = raw[..header_end].iter().position(|&b| b == b' ')?;
+        let kind = ObjectKind::from_name(&raw[..kind_end])?;
+        Some((kind, raw[header_end + 1..].to_vec()))
+    }
+
+    fn read_packed(
+        &mut self,
+        pack: usize,
+        offset: u64,
+        depth: usize,
+    ) -> Option<(ObjectKind, Vec<u8>)> {
+        if depth > MAX_DELTA_CHAIN {
+            return None;
+        }
+        if let Some(cached) = self.bases.get(&(pack, offset)) {
+            return Some(cached.clone());
+        }
+
+        let entry = self.packs.as_ref()?.get(pack)?.read_entry(offset)?;
+        let mut pos = 0;
+        let mut byte = *entry.first()?;
+        let kind = (byte >> 4) & 0x7;
+        let mut size = (byte & 0x0f) as usize;
+        let mut shift = 4;
+        while byte & 0x80 != 0 {
+            pos += 1;
+            byte = *entry.get(pos)?;
+            size |= ((byte & 0x7f) as usize) << shift;
+            shift += 7;
+        }
+        pos += 1;
+
+        let object = match kind {
+            PACK_OFS_DELTA => {
+                let back = read_offset_varint(&entry, &mut pos)?;
+                let delta = decompress_to_vec_zlib_with_limit(entry.get(pos..)?, size).ok()?;
+                let (base_kind, base) =
+                    self.read_packed(pack, offset.checked_sub(back)?, depth + 1)?;
+                (base_kind, apply_delta(&base, &delta)?)
+            }
+            PACK_REF_DELTA => {
+                let base_id = read_id(&entry, pos)?;
+                let delta = decompress_to_vec_zlib_with_limit(entry.get(pos + 20..)?, size).ok()?;
+                let (base_kind, base) = self.read_object_at(&base_id, depth + 1)?;
+                (base_kind, apply_delta(&base, &delta)?)
+            }
+            _ => {
+                let kind = ObjectKind::from_pack_type(kind)?;
+                (
+                    kind,
+                    decompress_to_vec_zlib_with_limit(entry.get(pos..)?, size).ok()?,
+                )
+            }
+        };
+
+        if depth > 0 && object.0 != ObjectKind::Blob {
+            if self.bases.len() >= MAX_CACHED_BASES {
+                self.bases.clear();
+            }
+            self.bases.insert((pack, offset), object.clone());
+        }
+        Some(object)
+    }
+
+    pub fn read_object(&mut self, id: &ObjectId) -> Option<(ObjectKind, Vec<u8>)> {
+        self.read_object_at(id, 0)
+    }
+
+    // Ref-delta bases re-enter here, so the chain depth carries across lookups by id
+    fn read_object_at(&mut self, id: &ObjectId, depth: usize) -> Option<(ObjectKind, Vec<u8>)> {
+        if let Some(object) = self.read_loose(id) {
+            return Some(object);
+        }
+
+        self.load_packs();
+        let found = self
+            .packs
+            .as_ref()?
+            .iter()
+            .enumerate()
+            .find_map(|(i, pack)| pack.find(id).map(|offset| (i, offset)));
+        let (pack, offset) = found?;
+        self.read_packed(pack, offset, depth)
+    }
+}
+
+fn resolve_ref(common_dir: &Path, name: &str, depth: usize) -> Option<ObjectId> {
+    if depth > 8 {
+        return None;
+    }
+
+    if let Ok(content) = fs::read_to_string(common_dir.join(name)) {
+        return match content.trim().strip_prefix("ref:") {
+            Some(target) => resolve_ref(common_dir, target.trim(), depth + 1),
+            None => parse_hex_id(&content),
+        };
+    }
+
+    let packed = fs::read_to_string(common_dir.join("packed-refs")).ok()?;
+    packed
+        .lines()
+        .filter(|line| !line.starts_with('#') && !line.starts_with('^'))
+        .find_map(|line| {
+            let (hex, ref_name) = line.split_once(' ')?;
+            (ref_name.trim() == name)
+                .then(|| parse_hex_id(hex))
+                .flatten()
+        })
+}
+
+// The commit HEAD points at, or None on an unborn branch
+pub fn read_head(git_dir: &Path) -> Option<ObjectId> {
+    let content = fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    let common_dir = get_common_dir(git_dir);
+    match content.trim().strip_prefix("ref:") {
+        // HEAD itself is per worktree, but the branch it names lives in the common dir
+        Some(name) => resolve_ref(&common_dir, name.trim(), 0),
+        None => parse_hex_id(&content),
+    }
+}
+
+fn parse_tree(data: &[u8]) -> Option<Vec<(u32, String, ObjectId)>> {
+    let mut entries = Vec::new();
+    let mut pos = 0;
+
+    while pos < data.len() {
+        let space = pos + data[pos..].iter().position(|&b| b == b' ')?;
+        let nul = space + data[space..].iter().position(|&b| b == 0)?;
+        let mode = u32::from_str_radix(std::str::from_utf8(&data[pos..space]).ok()?, 8).ok()?;
+        let name = String::from_utf8_lossy(&data[space + 1..nul]).into_owned();
+        let id = read_id(data, nul + 1)?;
+        entries.push((mode, name, id));
+        pos = nul + 21;
+    }
+
+    Some(entries)
+}
+
+type TreeEntry = (u32, String, ObjectId);
+
+// HEAD's tree, read one directory at a time as listings reach it, so a listing deep in a
+// large repository only inflates the trees on the way down and below
+pub struct HeadTree {
+    store: ObjectStore,
+    root: Option<ObjectId>,
+    // None for paths that are not a directory in HEAD
+    dirs: HashMap<String, Option<Vec<TreeEntry>>>,
+}
+
+fn split_parent(rel: &str) -> (&str, &str) {
+    rel.rsplit_once('/').unwrap_or(("", rel))
+}
+
+impl HeadTree {
+    // An unborn branch or an unreadable commit reads as an empty tree
+    pub fn open(git_dir: &Path) -> Self {
+        let mut store = ObjectStore::new(get_common_dir(git_dir).join("objects"));
+        let root = read_head(git_dir).and_then(|commit| {
+            let (kind, data) = store.read_object(&commit)?;
+            if kind != ObjectKind::Commit {
+                return None;
+            }
+            let tree_hex = data.strip_prefix(b"tree ")?.get(..40)?;
+            parse_hex_id(std::str::from_utf8(tree_hex).ok()?)
+        });
+
+        Self {
+            store,
+            root,
+            dirs: HashMap::new(),
+        }
+    }
+
+    fn read_dir(&mut self, rel_dir: &str) -> Option<&[TreeEntry]> {
+        if !self.dirs.contains_key(rel_dir) {
+            let entries = self.load_dir(rel_dir);
+            self.dirs.insert(rel_dir.to_string(), entries);
+        }
+        self.dirs.get(rel_dir)?.as_deref()
+    }
+
+    fn load_dir(&mut self, rel_dir: &str) -> Option<Vec<TreeEntry>> {
+        let id = if rel_dir.is_empty() {
+            self.root?
+        } else {
+            let (parent, name) = split_parent(rel_dir);
+            self.read_dir(parent)?
+                .iter()
+                .find(|(mode, entry, _)| *mode == MODE_TREE && entry == name)
+                .map(|&(_, _, id)| id)?
+        };
+
+        let (kind, data) = self.store.read_object(&id)?;
+        if kind != ObjectKind::Tree {
+            return None;
+        }
+        parse_tree(&data)
+    }
+
+    pub fn get(&mut self, rel: &str) -> Option<(u32, ObjectId)> {
+        let (parent, name) = split_parent(rel);
+        self.read_dir(parent)?
+            .iter()
+            .find(|(mode, entry, _)| *mode != MODE_TREE && entry == name)
+            .map(|&(mode, _, id)| (mode, id))
+    }
+
+    // Every non-tree path below the directory, reading its subtrees on the way
+    pub fn get_paths_below(&mut self, rel_dir: &str) -> Vec<String> {
+        let mut paths = Vec::new();
+        self.collect_paths(rel_dir, &mut paths);
+        paths
+    }
+
+    fn collect_paths(&mut self, rel_dir: &str, out: &mut Vec<String>) {
+        let entries = match self.read_dir(rel_dir) {
+            Some(entries) => entries.to_vec(),
+            None => return,
+        };
+
+        for (mode, name, _) in entries {
+            let path = if rel_dir.is_empty() {
+                name
+            } else {
+                format!("{}/{}", rel_dir, name)
+            };
+            if mode == MODE_TREE {
+                self.collect_paths(&path, out);
+            } else {
+                out.push(path);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use miniz_oxide::deflate::compress_to_vec_zlib;
+    use tempfile::TempDir;
+
+    pub fn hash_object(kind: &str, content: &[u8]) -> ObjectId {
+        let mut hasher = sha1_smol::Sha1::new();
+        hasher.update(format!("{} {}\0", kind, content.len()).as_bytes());
+        hasher.update(content);
+        hasher.digest().bytes()
+    }
+
+    pub fn write_loose(git_dir: &Path, kind: &str, content: &[u8]) -> ObjectId {
+        let id = hash_object(kind, content);
+        let hex = to_hex(&id);
+        let dir = git_dir.join("objects").join(&hex[..2]);
+        fs::create_dir_all(&dir).unwrap();
+        let mut raw = format!("{} {}\0", kind, content.len()).into_bytes();
+        raw.extend_from_slice(content);
+        fs::write(dir.join(&hex[2..]), compress_to_vec_zlib(&raw, 6)).unwrap();
+        id
+    }
+
+    pub fn tree_bytes(entries: &[(&str, &str, ObjectId)]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for (mode, name, id) in entries {
+            data.extend_from_slice(format!("{} {}\0", mode, name).as_bytes());
+            data.extend_from_slice(id);
+        }
+        data
+    }
+
+    // A version 2 index; each entry is (path, mode, size, mtime secs, blob id, stage)
+    pub fn index_bytes(entries: &[(&str, u32, u32, u32, ObjectId, u8)]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(INDEX_SIGNATURE);
+        data.extend_from_slice(&2u32.to_be_bytes());
+        data.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+        for (path, mode, size, mtime, id, stage) in entries {
+            let start = data.len();
+            for value in [0, 0, *mtime, 0, 0, 0, *mode, 0, 0, *size] {
+                data.extend_from_slice(&value.to_be_bytes());
+            }
+            data.extend_from_slice(id);
+            let flags = ((*stage as u16) << 12) | path.len().min(0xfff) as u16;
+            data.extend_from_slice(&flags.to_be_bytes());
+            data.extend_from_slice(path.as_bytes());
+            let padded = (data.len() - start + 8) / 8 * 8;
+            data.resize(start + padded, 0);
+        }
+        data.extend_from_slice(&[0u8; 20]);
+        data
+    }
+
+    fn pack_header(kind: u8, size: usize) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut byte = (kind << 4) | (size & 0x0f) as u8;
+        let mut rest = size >> 4;
+        while rest > 0 {
+            out.push(byte | 0x80);
+            byte = (rest & 0x7f) as u8;
+            rest >>= 7;
+        }
+        out.push(byte);
+        out
+    }
+
+    // A pack holding a blob and an OFS_DELTA on it, with its version 2 index
+    fn write_delta_pack(git_dir: &Path, base: &[u8], delta: &[u8], target: &[u8]) -> ObjectId {
+        let base_id = hash_object("blob", base);
+        let target_id = hash_object("blob", target);
+
+        let mut pack = b"PACK\0\0\0\x02\0\0\0\x02".to_vec();
+        let base_offset = pack.len() as u64;
+        pack.extend(pack_header(3, base.len()));
+        pack.extend(compress_to_vec_zlib(base, 6));
+        let delta_offset = pack.len() as u64;
+        pack.extend(pack_header(PACK_OFS_DELTA, delta.len()));
+        pack.push((delta_offset - base_offset) as u8);
+        pack.extend(compress_to_vec_zlib(delta, 6));
+        pack.extend_from_slice(&[0u8; 20]);
+
+        let mut objects = [(base_id, base_offset), (target_id, delta_offset)];
+        objects.sort();
+        let mut idx = PACK_IDX_SIGNATURE.to_vec();
+        idx.extend_from_slice(&2u32.to_be_bytes());
+        for b in 0..256usize {
+            let n = objects
+                .iter()
+                .filter(|(id, _)| (id[0] as usize) <= b)
+                .count() as u32;
+            idx.extend_from_slice(&n.to_be_bytes());
+        }
+        for (id, _) in &objects {
+            idx.extend_from_slice(id);
+        }
+        idx.extend_from_slice(&[0u8; 8]);
+        for (_, offset) in &objects {
+            idx.extend_from_slice(&(*offset as u32).to_be_bytes());
+        }
+
+        let pack_dir = git_dir.join("objects/pack");
+        fs::create_dir_all(&pack_dir).unwrap();
+        fs::write(pack_dir.join("pack-test.pack"), pack).unwrap();
+        fs::write(pack_dir.join("pack-test.idx"), idx).unwrap();
+        target_id
+    }
+
+    #[test]
+    fn test_parse_hex_id_roundtrip() {
+        let hex = "0123456789abcdef0123456789abcdef01234567";
+        assert_eq!(to_hex(&parse_hex_id(hex).unwrap()), hex);
+        assert!(parse_hex_id("0123").is_none());
+        assert!(parse_hex_id(&"zz".repeat(20)).is_none());
+    }
+
+    #[test]
+    fn test_hash_blob_matches_git() {
+        // `printf 'hello\n' | git hash-object --stdin`
+        assert_eq!(
+            to_hex(&hash_blob(b"hello\n")),
+            "ce013625030ba8dba906f756967f9e9ca394464a"
+        );
+    }
+
+    #[test]
+    fn test_parse_index_v2() {
+        let id = hash_blob(b"x");
+        let data = index_bytes(&[
+            ("a.txt", 0o100644, 1, 100, id, 0),
+            ("dir/long_name.rs", 0o100755, 2, 200, id, 0),
+            ("merge.txt", 0o100644, 3, 300, id, 2),
+        ]);
+        let entries = parse_index(&data).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[1].0, "dir/long_name.rs");
+        assert_eq!(entries[1].1.mode, 0o100755);
+        assert_eq!(entries[1].1.mtime, (200, 0));
+        assert_eq!(entries[2].1.stage, 2);
+    }
+
+    #[test]
+    fn test_parse_index_v4_prefix_compression() {
+        let id = hash_blob(b"x");
+        let mut data = INDEX_SIGNATURE.to_vec();
+        data.extend_from_slice(&4u32.to_be_bytes());
+        data.extend_from_slice(&2u32.to_be_bytes());
+        for (strip, suffix) in [(0u8, "src/main.rs"), (7, "lib.rs")] {
+            data.extend_from_slice(&[0u8; 24]);
+            data.extend_from_slice(&0o100644u32.to_be_bytes());
+            data.extend_from_slice(&[0u8; 12]);
+            data.extend_from_slice(&id);
+            data.extend_from_slice(&0u16.to_be_bytes());
+            data.push(strip);
+            data.extend_from_slice(suffix.as_bytes());
+            data.push(0);
+        }
+        let entries = parse_index(&data).unwrap();
+        assert_eq!(entries[0].0, "src/main.rs");
+        assert_eq!(entries[1].0, "src/lib.rs");
+    }
+
+    #[test]
+    fn test_parse_index_rejects_split_and_sparse() {
+        let id = hash_blob(b"x");
+        let with_extension = |signature: &[u8]| {
+            let mut data = index_bytes(&[("a.txt", 0o100644, 1, 100, id, 0)]);
+            let checksum = data.split_off(data.len() - INDEX_CHECKSUM);
+            data.extend_from_slice(signature);
+            data.extend_from_slice(&4u32.to_be_bytes());
+            data.extend_from_slice(&[0u8; 4]);
+            data.extend_from_slice(&checksum);
+            data
+        };
+        assert!(parse_index(&with_extension(b"TREE")).is_some());
+        assert!(parse_index(&with_extension(b"link")).is_none());
+        assert!(parse_index(&with_extension(b"sdir")).is_none());
+
+        let sparse = index_bytes(&[("vendor/", MODE_TREE, 0, 100, id, 0)]);
+        assert!(parse_index(&sparse).is_none());
+    }
+
+    #[test]
+    fn test_parse_index_rejects_garbage() {
+        assert!(parse_index(b"nope").is_none());
+        assert!(parse_index(b"DIRC\0\0\0\x09\0\0\0\0").is_none());
+        assert!(parse_index(b"DIRC\0\0\0\x02\xff\xff\xff\xff").is_none());
+    }
+
+    #[test]
+    fn test_apply_delta_copy_and_insert() {
+        let base = b"hello world";
+        // sizes 11 -> 12, copy 6 bytes from 0, insert "there!"
+        let delta = [11, 12, 0x90, 6, 6, b't', b'h', b'e', b'r', b'e', b'!'];
+        assert_eq!(apply_delta(base, &delta).unwrap(), b"hello there!");
+    }
+
+    #[test]
+    fn test_apply_delta_size_mismatch() {
+        assert!(apply_delta(b"abc", &[4, 1, 1, b'x']).is_none());
+        assert!(apply_delta(b"abc", &[3, 5, 1, b'x']).is_none());
+    }
+
+    #[test]
+    fn test_read_loose_object() {
+        let dir = TempDir::new().unwrap();
+        let id = write_loose(dir.path(), "blob", b"content\n");
+        let mut store = ObjectStore::new(dir.path().join("objects"));
+        let (kind, data) = store.read_object(&id).unwrap();
+        assert_eq!(kind, ObjectKind::Blob);
+        assert_eq!(data, b"content\n");
+    }
+
+    #[test]
+    fn test_read_packed_delta_object() {
+        let dir = TempDir::new().unwrap();
+        let base = b"hello world";
+        let delta = [11, 12, 0x90, 6, 6, b't', b'h', b'e', b'r', b'e', b'!'];
+        let id = write_delta_pack(dir.path(), base, &delta, b"hello there!");
+
+        let mut store = ObjectStore::new(dir.path().join("objects"));
+        let (kind, data) = store.read_object(&id).unwrap();
+        assert_eq!(kind, ObjectKind::Blob);
+        assert_eq!(data, b"hello there!");
+        assert!(store.read_object(&hash_blob(b"missing")).is_none());
+    }
+
+    #[test]
+    fn test_head_tree_via_branch() {
+        let dir = TempDir::new().unwrap();
+        let git_dir = dir.path();
+        let blob = write_loose(git_dir, "blob", b"fn main() {}\n");
+        let sub = write_loose(git_dir, "tree", &tree_bytes(&[("100644", "lib.rs", blob)]));
+        let root = write_loose(
+            git_dir,
+            "tree",
+            &tree_bytes(&[("100755", "run.sh", blob), ("40000", "src", sub)]),
+        );
+        let commit = format!("tree {}\nauthor a <a> 0 +0000\n\nmsg\n", to_hex(&root));
+        let commit_id = write_loose(git_dir, "commit", commit.as_bytes());
+
+        fs::create_dir_all(git_dir.join("refs/heads")).unwrap();
+        fs::write(git_dir.join("HEAD"), "ref: refs/heads/main\n").unwrap();
+        fs::write(
+            git_dir.join("packed-refs"),
+            format!("# pack-refs\n{} refs/heads/main\n", to_hex(&commit_id)),
+        )
+        .unwrap();
+
+        let mut head = HeadTree::open(git_dir);
+        assert_eq!(head.get("run.sh"), Some((0o100755, blob)));
+        assert_eq!(head.get("src"), None);
+        // Only the root tree has been read so far
+        assert_eq!(head.dirs.len(), 1);
+        assert_eq!(head.get("src/lib.rs"), Some((0o100644, blob)));
+        assert_eq!(head.get_paths_below(""), ["run.sh", "src/lib.rs"]);
+        assert!(head.get_paths_below("missing").is_empty());
+    }
+
+    #[test]
+    fn test_head_tree_unborn_branch() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("HEAD"), "ref: refs/heads/main\n").unwrap();
+        let mut head = HeadTree::open(dir.path());
+        assert!(head.get_paths_below("").is_empty());
+        assert_eq!(head.get("a.txt"), None);
+    }
+
+    #[test]
+    fn test_resolve_git_dir_file() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join(".git"), "gitdir: ../real/.git\n").unwrap();
+        assert_eq!(
+            resolve_git_dir(dir.path()).unwrap(),
+            dir.path().join("../real/.git")
+        );
+    }
+
+    #[test]
+    fn test_find_repo_root_walks_up() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join(".git")).unwrap();
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        let root = find_repo_root(&dir.path().join("a/b")).unwrap();
+        assert_eq!(root, fs::canonicalize(dir.path()).unwrap());
+    }
+}
@@ -0,0 +1,570 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use crate::git::{
+    find_repo_root, hash_blob, parse_index, resolve_git_dir, HeadTree, IndexEntry, MODE_GITLINK,
+    MODE_SYMLINK,
+};
+use crate::gitignore::{DirIgnore, GitIgnore};
+
+const CLEAN: char = '-';
+
+// Higher wins when a directory combines the states of its contents
+fn get_priority(state: char) -> u8 {
+    match state {
+        'U' => 6,
+        'M' => 5,
+        'D' => 4,
+        'A' => 3,
+        '?' => 2,
+        '!' => 1,
+        _ => 0,
+    }
+}
+
+// Two letters like `git status --short`: the staged change, then the unstaged one.
+// `??` is untracked, `!!` ignored, `UU` conflicted and `-` means no change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GitStatus {
+    pub staged: char,
+    pub unstaged: char,
+}
+
+impl GitStatus {
+    pub const CLEAN: Self = Self::new(CLEAN, CLEAN);
+    pub const UNTRACKED: Self = Self::new('?', '?');
+    pub const IGNORED: Self = Self::new('!', '!');
+    pub const CONFLICTED: Self = Self::new('U', 'U');
+
+    const fn new(staged: char, unstaged: char) -> Self {
+        Self { staged, unstaged }
+    }
+
+    fn combine(self, other: Self) -> Self {
+        let pick = |a: char, b: char| {
+            if get_priority(b) > get_priority(a) {
+                b
+            } else {
+                a
+            }
+        };
+        Self::new(
+            pick(self.staged, other.staged),
+            pick(self.unstaged, other.unstaged),
+        )
+    }
+}
+
+impl fmt::Display for GitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.staged, self.unstaged)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct TrackedEntry {
+    entry: IndexEntry,
+    conflicted: bool,
+}
+
+pub struct GitRepo {
+    root: PathBuf,
+    index: BTreeMap<String, TrackedEntry>,
+    head: Mutex<HeadTree>,
+    // Files changed in the same second the index was written can't be trusted by stat alone
+    index_mtime: i64,
+    ignore: GitIgnore,
+    // Rules per directory relative to the root; siblings and nested checks share them
+    dir_ignores: Mutex<HashMap<String, Arc<DirIgnore>>>,
+    // Each file is checked once; directory rows fold their aggregates from here
+    file_statuses: Mutex<HashMap<String, GitStatus>>,
+    // Whether a directory holds untracked files, shared by all of its ancestors
+    untracked_dirs: Mutex<HashMap<String, bool>>,
+}
+
+// Computed without holding the lock; two workers racing on one key just compute it twice
+fn get_cached<T: Clone>(
+    cache: &Mutex<HashMap<String, T>>,
+    key: &str,
+    compute: impl FnOnce() -> T,
+) -> T {
+    let lock = || cache.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(value) = lock().get(key) {
+        return value.clone();
+    }
+
+    let value = compute();
+    lock().insert(key.to_string(), value.clone());
+    value
+}
+
+fn join_rel(base: &str, name: &str) -> String {
+    if base.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", base, name)
+    }
+}
+
+fn get_prefix(rel_dir: &str) -> String {
+    if rel_dir.is_empty() {
+        String::new()
+    } else {
+        format!("{}/", rel_dir)
+    }
+}
+
+// Mode as git records it: only the owner execute bit survives for regular files
+fn get_worktree_mode(meta: &fs::Metadata) -> u32 {
+    if meta.file_type().is_symlink() {
+        MODE_SYMLINK
+    } else if meta.permissions().mode() & 0o100 != 0 {
+        0o100755
+    } else {
+        0o100644
+    }
+}
+
+impl GitRepo {
+    pub fn open(start: &Path) -> Option<Self> {
+        let root = find_repo_root(start)?;
+        let git_dir = resolve_git_dir(&root)?;
+
+        let index_path = git_dir.join("index");
+        let index_mtime = fs::metadata(&index_path).map(|m| m.mtime()).unwrap_or(0);
+        let mut index: BTreeMap<String, TrackedEntry> = BTreeMap::new();
+
+        // No index yet is an empty one; one that can't be read fully means no column at all
+        let entries = match fs::read(&index_path) {
+            Ok(data) => parse_index(&data)?,
+            Err(_) => Vec::new(),
+        };
+        for (path, entry) in entries {
+            let conflicted = entry.stage != 0;
+            index
+                .entry(path)
+                .and_modify(|tracked| tracked.conflicted |= conflicted)
+                .or_insert(TrackedEntry { entry, conflicted });
+        }
+
+        let head = Mutex::new(HeadTree::open(&git_dir));
+
+        Some(Self {
+            ignore: GitIgnore::new(root.clone()),
+            root,
+            index,
+            head,
+            index_mtime,
+            dir_ignores: Mutex::new(HashMap::new()),
+            file_statuses: Mutex::new(HashMap::new()),
+            untracked_dirs: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn get_rel_path(&self, path: &Path) -> Option<String> {
+        // The entry itself may be a symlink, so only its parent is resolved
+        let parent = match path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            Some(parent) => fs::canonicalize(parent).ok()?,
+            None => std::env::current_dir().ok()?,
+        };
+        let full = parent.join(path.file_name()?);
+        let rel = full.strip_prefix(&self.root).ok()?;
+        Some(rel.to_string_lossy().into_owned())
+    }
+
+    fn get_dir_ignore(&self, rel_dir: &str) -> Arc<DirIgnore> {
+        get_cached(&self.dir_ignores, rel_dir, || {
+            Arc::new(self.ignore.for_dir(&self.root.join(rel_dir)))
+        })
+    }
+
+    fn is_ignored(&self, rel: &str, is_dir: bool) -> bool {
+        let (parent, name) = rel.rsplit_once('/').unwrap_or(("", rel));
+        self.get_dir_ignore(parent).is_ignored(name, is_dir)
+    }
+
+    fn get_unstaged(&self, rel: &str, tracked: &TrackedEntry) -> char {
+        let entry = &tracked.entry;
+        if entry.skip_worktree {
+            return CLEAN;
+        }
+
+        let path = self.root.join(rel);
+        let meta = match fs::symlink_metadata(&path) {
+            Ok(meta) => meta,
+            Err(_) => return 'D',
+        };
+
+        if entry.mode == MODE_GITLINK {
+            return if meta.is_dir() { CLEAN } else { 'D' };
+        }
+        if meta.is_dir() {
+            return 'D';
+        }
+        if get_worktree_mode(&meta) != entry.mode || meta.size() as u32 != entry.size {
+            return 'M';
+        }
+
+        let same_mtime = meta.mtime() as u32 == entry.mtime.0
+            && meta.mtime_nsec() as u32 == entry.mtime.1
+            && (entry.mtime.0 as i64) < self.index_mtime;
+        if same_mtime {
+            return CLEAN;
+        }
+
+        let content = if meta.file_type().is_symlink() {
+            fs::read_link(&path).map(|t| t.as_os_str().as_encoded_bytes().to_vec())
+        } else {
+            fs::read(&path)
+        };
+        match content {
+            Ok(content) if hash_blob(&content) == entry.id => CLEAN,
+            _ => 'M',
+        }
+    }
+
+    fn get_file_status(&self, rel: &str) -> GitStatus {
+        get_cached(&self.file_statuses, rel, || self.read_file_status(rel))
+    }
+
+    fn get_head(&self) -> MutexGuard<'_, HeadTree> {
+        self.head.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn read_file_status(&self, rel: &str) -> GitStatus {
+        let head = self.get_head().get(rel);
+        match (self.index.get(rel), head) {
+            (Some(tracked), _) if tracked.conflicted => GitStatus::CONFLICTED,
+            (Some(tracked), head) => {
+                let staged = match head {
+                    None => 'A',
+                    Some((mode, id)) if mode != tracked.entry.mode || id != tracked.entry.id => 'M',
+                    Some(_) => CLEAN,
+                };
+                GitStatus::new(staged, self.get_unstaged(rel, tracked))
+            }
+            (None, Some(_)) => {
+                let exists = fs::symlink_metadata(self.root.join(rel)).is_ok();
+                GitStatus::new('D', if exists { '?' } else { CLEAN })
+            }
+            (None, None) if self.is_ignored(rel, false) => GitStatus::IGNORED,
+            (None, None) => GitStatus::UNTRACKED,
+        }
+    }
+
+    fn get_tracked_below(&self, rel_dir: &str) -> Vec<String> {
+        let prefix = get_prefix(rel_dir);
+        let mut paths: Vec<String> = self
+            .index
+            .range(prefix.clone()..)
+            .map(|(path, _)| path)
+            .take_while(|path| path.starts_with(&prefix))
+            .cloned()
+            .collect();
+        paths.extend(self.get_head().get_paths_below(rel_dir));
+        paths.sort_unstable();
+        paths.dedup();
+        paths
+    }
+
+    fn has_untracked(&self, rel_dir: &str) -> bool {
+        get_cached(&self.untracked_dirs, rel_dir, || {
+            self.find_untracked(rel_dir)
+        })
+    }
+
+    // Stops at the first file that is neither tracked nor ignored
+    fn find_untracked(&self, rel_dir: &str) -> bool {
+        let rules = self.get_dir_ignore(rel_dir);
+        let entries = match fs::read_dir(self.root.join(rel_dir)) {
+            Ok(entries) => entries,
+            Err(_) => return false,
+        };
+
+        entries.filter_map(|e| e.ok()).any(|e| {
+            let name = e.file_name().to_string_lossy().into_owned();
+            let is_dir = e.file_type().is_ok_and(|t| t.is_dir());
+            let rel = join_rel(rel_dir, &name);
+
+            if rules.is_ignored(&name, is_dir) || self.index.contains_key(&rel) {
+                return false;
+            }
+            !is_dir || self.has_untracked(&rel)
+        })
+    }
+
+    fn get_dir_status(&self, rel: &str) -> GitStatus {
+        let tracked = self.get_tracked_below(rel);
+
+        if tracked.is_empty() {
+            if !rel.is_empty() && self.is_ignored(rel, true) {
+                return GitStatus::IGNORED;
+            }
+            if self.has_untracked(rel) {
+                return GitStatus::UNTRACKED;
+            }
+            return GitStatus::CLEAN;
+        }
+
+        let status = tracked
+            .iter()
+            .map(|path| self.get_file_status(path))
+            .fold(GitStatus::CLEAN, GitStatus::combine);
+
+        // New files inside a tracked directory only mark the unstaged side
+        if status.unstaged == CLEAN && self.has_untracked(rel) {
+            return GitStatus::new(status.staged, '?');
+        }
+        status
+    }
+
+    // None for paths outside the work tree and for `.git` itself
+    pub fn get_status(&self, path: &Path) -> Option<GitStatus> {
+        let rel = self.get_rel_path(path)?;
+        if rel.split('/').any(|part| part == ".git") {
+            return None;
+        }
+
+        let is_dir = fs::symlink_metadata(path).is_ok_and(|m| m.is_dir());
+        let is_gitlink = self
+            .index
+            .get(&rel)
+            .is_some_and(|t| t.entry.mode == MODE_GITLINK);
+
+        if is_dir && !is_gitlink {
+            Some(self.get_dir_status(&rel))
+        } else {
+            Some(self.get_file_status(&rel))
+        }
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.get_rel_path(path).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::tests::{index_bytes, tree_bytes, write_loose};
+    use crate::git::to_hex;
+    use std::fs::File;
+    use std::time::{Duration, SystemTime};
+    use tempfile::TempDir;
+
+    struct TestRepo {
+        dir: TempDir,
+    }
+
+    impl TestRepo {
+        fn path(&self, rel: &str) -> PathBuf {
+            self.dir.path().join(rel)
+        }
+
+        fn open(&self) -> GitRepo {
+            GitRepo::open(self.dir.path()).unwrap()
+        }
+
+        fn status(&self, rel: &str) -> String {
+            self.open().get_status(&self.path(rel)).unwrap().to_string()
+        }
+    }
+
+    fn set_mtime(path: &Path, secs: u64) {
+        let file = File::options().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+            .unwrap();
+    }
+
+    // HEAD has `src/lib.rs` and `old.txt`; the index adds `new.txt`, drops `old.txt`
+    // and holds `conflict.txt` at stage 2. Everything on disk matches the index.
+    fn make_repo() -> TestRepo {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        let git_dir = root.join(".git");
+        fs::create_dir_all(git_dir.join("refs/heads")).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("build")).unwrap();
+        fs::write(root.join(".gitignore"), "build/\n").unwrap();
+
+        let lib = b"pub fn lib() {}\n";
+        let new = b"new\n";
+        fs::write(root.join("src/lib.rs"), lib).unwrap();
+        fs::write(root.join("new.txt"), new).unwrap();
+        fs::write(root.join("conflict.txt"), b"<<<<\n").unwrap();
+        fs::write(root.join("build/out.o"), b"obj").unwrap();
+        for file in ["src/lib.rs", "new.txt", "conflict.txt"] {
+            set_mtime(&root.join(file), 1000);
+        }
+
+        let lib_id = write_loose(&git_dir, "blob", lib);
+        let new_id = write_loose(&git_dir, "blob", new);
+        let old_id = write_loose(&git_dir, "blob", b"old\n");
+        let src = write_loose(
+            &git_dir,
+            "tree",
+            &tree_bytes(&[("100644", "lib.rs", lib_id)]),
+        );
+        let top = write_loose(
+            &git_dir,
+            "tree",
+            &tree_bytes(&[("100644", "old.txt", old_id), ("40000", "src", src)]),
+        );
+        let commit = format!("tree {}\n\nmsg\n", to_hex(&top));
+        let commit_id = write_loose(&git_dir, "commit", commit.as_bytes());
+        fs::write(git_dir.join("refs/heads/main"), to_hex(&commit_id)).unwrap();
+        fs::write(git_dir.join("HEAD"), "ref: refs/heads/main\n").unwrap();
+
+        let index = index_bytes(&[
+            ("conflict.txt", 0o100644, 5, 1000, old_id, 2),
+            ("new.txt", 0o100644, 4, 1000, new_id, 0),
+            ("src/lib.rs", 0o100644, 16, 1000, lib_id, 0),
+        ]);
+        fs::write(git_dir.join("index"), index).unwrap();
+
+        TestRepo { dir }
+    }
+
+    #[test]
+    fn test_combine_prefers_stronger_state() {
+        let modified = GitStatus::new(CLEAN, 'M');
+        let added = GitStatus::new('A', CLEAN);
+        assert_eq!(modified.combine(added), GitStatus::new('A', 'M'));
+        assert_eq!(GitStatus::CLEAN.combine(GitStatus::CLEAN), GitStatus::CLEAN);
+        assert_eq!(added.combine(GitStatus::CONFLICTED), GitStatus::CONFLICTED);
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(GitStatus::UNTRACKED.to_string(), "??");
+        assert_eq!(GitStatus::new('M', CLEAN).to_string(), "M-");
+    }
+
+    #[test]
+    fn test_clean_tracked_file() {
+        let repo = make_repo();
+        assert_eq!(repo.status("src/lib.rs"), "--");
+    }
+
+    #[test]
+    fn test_staged_new_file() {
+        let repo = make_repo();
+        assert_eq!(repo.status("new.txt"), "A-");
+    }
+
+    #[test]
+    fn test_modified_same_size_detected_by_hash() {
+        let repo = make_repo();
+        fs::write(repo.path("src/lib.rs"), b"pub fn lib() {}/\n").unwrap();
+        assert_eq!(repo.status("src/lib.rs"), "-M");
+    }
+
+    #[test]
+    fn test_touched_but_unchanged_is_clean() {
+        let repo = make_repo();
+        set_mtime(&repo.path("src/lib.rs"), 2000);
+        assert_eq!(repo.status("src/lib.rs"), "--");
+    }
+
+    #[test]
+    fn test_worktree_deletion_and_mode_change() {
+        let repo = make_repo();
+        let mut perms = fs::metadata(repo.path("new.txt")).unwrap().permissions();
+        perms.set_mode(0o755);
+        fs::set_permissions(repo.path("new.txt"), perms).unwrap();
+        assert_eq!(repo.status("new.txt"), "AM");
+
+        fs::remove_file(repo.path("src/lib.rs")).unwrap();
+        assert_eq!(
+            repo.open().get_file_status("src/lib.rs"),
+            GitStatus::new(CLEAN, 'D')
+        );
+    }
+
+    #[test]
+    fn test_staged_deletion() {
+        let repo = make_repo();
+        assert_eq!(
+            repo.open().get_file_status("old.txt"),
+            GitStatus::new('D', CLEAN)
+        );
+    }
+
+    #[test]
+    fn test_conflicted_untracked_ignored() {
+        let repo = make_repo();
+        File::create(repo.path("notes.md")).unwrap();
+        assert_eq!(repo.status("conflict.txt"), "UU");
+        assert_eq!(repo.status("notes.md"), "??");
+        assert_eq!(repo.status("build/out.o"), "!!");
+        assert_eq!(repo.status("build"), "!!");
+    }
+
+    #[test]
+    fn test_dir_aggregates_contents() {
+        let repo = make_repo();
+        assert_eq!(repo.status("src"), "--");
+
+        File::create(repo.path("src/extra.rs")).unwrap();
+        assert_eq!(repo.status("src"), "-?");
+
+        fs::write(repo.path("src/lib.rs"), b"changed\n").unwrap();
+        assert_eq!(repo.status("src"), "-M");
+    }
+
+    #[test]
+    fn test_file_status_checked_once() {
+        let repo = make_repo();
+        File::create(repo.path("src/extra.rs")).unwrap();
+        let git = repo.open();
+        assert_eq!(git.get_status(&repo.path("src")).unwrap().to_string(), "-?");
+
+        // Later rows reuse what the directory row already worked out
+        fs::write(repo.path("src/lib.rs"), b"changed\n").unwrap();
+        fs::remove_file(repo.path("src/extra.rs")).unwrap();
+        let lib = git.get_status(&repo.path("src/lib.rs")).unwrap();
+        assert_eq!(lib, GitStatus::CLEAN);
+        assert!(git.has_untracked("src"));
+    }
+
+    #[test]
+    fn test_untracked_dir() {
+        let repo = make_repo();
+        fs::create_dir_all(repo.path("docs/api")).unwrap();
+        assert_eq!(repo.status("docs"), "--");
+        File::create(repo.path("docs/api/index.md")).unwrap();
+        assert_eq!(repo.status("docs"), "??");
+    }
+
+    #[test]
+    fn test_dir_ignore_read_once() {
+        let repo = make_repo();
+        let git = repo.open();
+        let first = git.get_dir_ignore("build");
+        assert!(Arc::ptr_eq(&first, &git.get_dir_ignore("build")));
+        assert!(first.is_ignored("out.o", false));
+    }
+
+    #[test]
+    fn test_unreadable_index_has_no_column() {
+        let repo = make_repo();
+        fs::write(repo.path(".git/index"), b"DIRC\0\0\0\x02\0\0\0\x09").unwrap();
+        assert!(GitRepo::open(repo.dir.path()).is_none());
+
+        fs::remove_file(repo.path(".git/index")).unwrap();
+        assert_eq!(repo.status("src/lib.rs"), "D?");
+    }
+
+    #[test]
+    fn test_git_dir_and_outside_paths() {
+        let repo = make_repo();
+        let git = repo.open();
+        assert!(git.get_status(&repo.path(".git")).is_none());
+        assert!(git.get_status(Path::new("/")).is_none());
+        assert!(!git.contains(Path::new("/tmp")));
+    }
+}
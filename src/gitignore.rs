@@ -0,0 +1,384 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::filters::glob_match;
+use crate::git::{find_repo_root, get_common_dir, resolve_git_dir};
+
+const IGNORE_FILES: [&str; 2] = [".gitignore", ".ignore"];
+
+#[derive(Debug, Clone, PartialEq)]
+struct IgnoreRule {
+    // Directory of the ignore file, relative to the repository root ("" for the root)
+    base: String,
+    segments: Vec<String>,
+    negated: bool,
+    dir_only: bool,
+    anchored: bool,
+}
+
+impl IgnoreRule {
+    fn parse(line: &str, base: &str) -> Option<Self> {
+        let line = trim_trailing_spaces(line);
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let line = line
+            .strip_prefix("\\#")
+            .map(|rest| format!("#{}", rest))
+            .or_else(|| line.strip_prefix("\\!").map(|rest| format!("!{}", rest)))
+            .unwrap_or_else(|| line.to_string());
+
+        let dir_only = line.ends_with('/');
+        let pattern = line.trim_end_matches('/');
+        if pattern.is_empty() {
+            return None;
+        }
+
+        // A slash anywhere but the end ties the pattern to the ignore file's directory
+        let anchored = pattern.contains('/');
+        let segments = pattern
+            .trim_start_matches('/')
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .map(String::from)
+            .collect();
+
+        Some(Self {
+            base: base.to_string(),
+            segments,
+            negated,
+            dir_only,
+            anchored,
+        })
+    }
+
+    fn matches(&self, path: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+
+        let rel = if self.base.is_empty() {
+            path
+        } else {
+            match path
+                .strip_prefix(self.base.as_str())
+                .and_then(|p| p.strip_prefix('/'))
+            {
+                Some(rel) => rel,
+                None => return false,
+            }
+        };
+
+        if !self.anchored {
+            let name = rel.rsplit('/').next().unwrap_or(rel);
+            return glob_match(&self.segments[0], name);
+        }
+
+        let parts: Vec<&str> = rel.split('/').collect();
+        match_segments(&self.segments, &parts)
+    }
+}
+
+// `**` spans any number of directories, every other segment matches exactly one.
+// A trailing `**` only matches what is inside, not the directory itself.
+fn match_segments(pattern: &[String], parts: &[&str]) -> bool {
+    match pattern.first() {
+        None => parts.is_empty(),
+        Some(seg) if seg == "**" && pattern.len() == 1 => !parts.is_empty(),
+        Some(seg) if seg == "**" => {
+            (0..=parts.len()).any(|skip| match_segments(&pattern[1..], &parts[skip..]))
+        }
+        Some(seg) => {
+            !parts.is_empty()
+                && glob_match(seg, parts[0])
+                && match_segments(&pattern[1..], &parts[1..])
+        }
+    }
+}
+
+// Trailing spaces are dropped unless escaped with a backslash
+fn trim_trailing_spaces(line: &str) -> &str {
+    let trimmed = line.trim_end_matches(' ');
+    if trimmed.ends_with('\\') && trimmed.len() < line.len() {
+        &line[..trimmed.len() + 1]
+    } else {
+        trimmed
+    }
+}
+
+fn parse_rules(content: &str, base: &str) -> Vec<IgnoreRule> {
+    content
+        .lines()
+        .filter_map(|line| IgnoreRule::parse(line, base))
+        .collect()
+}
+
+fn read_rules(path: &Path, base: &str) -> Vec<IgnoreRule> {
+    fs::read_to_string(path)
+        .map(|content| parse_rules(&content, base))
+        .unwrap_or_default()
+}
+
+// The last matching rule decides, so a later `!pattern` can take a file back
+fn is_ignored_by(rules: &[IgnoreRule], path: &str, is_dir: bool) -> bool {
+    rules
+        .iter()
+        .rev()
+        .find(|rule| rule.matches(path, is_dir))
+        .is_some_and(|rule| !rule.negated)
+}
+
+#[derive(Debug, Clone)]
+pub struct GitIgnore {
+    root: PathBuf,
+    // `info/exclude` lives in the shared git dir, which worktrees and submodules keep elsewhere
+    exclude: Option<PathBuf>,
+}
+
+impl GitIgnore {
+    pub fn new(root: PathBuf) -> Self {
+        let exclude = resolve_git_dir(&root).map(|dir| get_common_dir(&dir).join("info/exclude"));
+        Self { root, exclude }
+    }
+
+    // Walks up from the start path to the first directory holding `.git`
+    pub fn find(start: &Path) -> Option<Self> {
+        find_repo_root(start).map(Self::new)
+    }
+
+    // Rules from `info/exclude`, then each ignore file from the root down to `dir`
+    pub fn for_dir(&self, dir: &Path) -> DirIgnore {
+        let rel_dir = fs::canonicalize(dir).ok().and_then(|d| {
+            d.strip_prefix(&self.root)
+                .ok()
+                .map(|p| p.to_string_lossy().into_owned())
+        });
+
+        let rel_dir = match rel_dir {
+            Some(rel) => rel,
+            None => return DirIgnore::default(),
+        };
+
+        let mut rules = match &self.exclude {
+            Some(path) => read_rules(path, ""),
+            None => Vec::new(),
+        };
+        let mut ancestor_ignored = false;
+        let mut base = String::new();
+
+        for part in std::iter::once("").chain(rel_dir.split('/').filter(|p| !p.is_empty())) {
+            if !part.is_empty() {
+                base = join_rel(&base, part);
+                // Git never looks inside an excluded directory, so nothing below it comes back
+                if part == ".git" || is_ignored_by(&rules, &base, true) {
+                    ancestor_ignored = true;
+                    break;
+                }
+            }
+            for file in IGNORE_FILES {
+                rules.extend(read_rules(&self.root.join(&base).join(file), &base));
+            }
+        }
+
+        DirIgnore {
+            rel_dir,
+            rules,
+            ancestor_ignored,
+            active: true,
+        }
+    }
+}
+
+fn join_rel(base: &str, name: &str) -> String {
+    if base.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", base, name)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DirIgnore {
+    rel_dir: String,
+    rules: Vec<IgnoreRule>,
+    ancestor_ignored: bool,
+    active: bool,
+}
+
+impl DirIgnore {
+    pub fn is_ignored(&self, name: &str, is_dir: bool) -> bool {
+        if !self.active {
+            return false;
+        }
+        if self.ancestor_ignored || name == ".git" {
+            return true;
+        }
+        is_ignored_by(&self.rules, &join_rel(&self.rel_dir, name), is_dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use tempfile::TempDir;
+
+    fn rule(line: &str) -> IgnoreRule {
+        IgnoreRule::parse(line, "").unwrap()
+    }
+
+    fn make_repo() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join(".git/info")).unwrap();
+        fs::create_dir_all(root.join("app/dist")).unwrap();
+        fs::create_dir_all(root.join("target/debug")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n*.log\n!keep.log\n").unwrap();
+        fs::write(root.join(".git/info/exclude"), "scratch.txt\n").unwrap();
+        fs::write(root.join("app/.gitignore"), "/dist\n").unwrap();
+        fs::write(root.join("app/.ignore"), "*.tmp\n").unwrap();
+        File::create(root.join("app/main.rs")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_rule_parse_skips_comments_and_blanks() {
+        assert!(IgnoreRule::parse("# comment", "").is_none());
+        assert!(IgnoreRule::parse("   ", "").is_none());
+        assert!(IgnoreRule::parse("/", "").is_none());
+    }
+
+    #[test]
+    fn test_rule_parse_flags() {
+        let r = rule("!build/");
+        assert!(r.negated);
+        assert!(r.dir_only);
+        assert!(!r.anchored);
+
+        let r = rule("/docs/*.md");
+        assert!(r.anchored);
+        assert_eq!(r.segments, vec!["docs", "*.md"]);
+    }
+
+    #[test]
+    fn test_rule_parse_escapes() {
+        assert_eq!(rule("\\#notes").segments, vec!["#notes"]);
+        assert!(!rule("\\!bang").negated);
+        assert_eq!(rule("\\!bang").segments, vec!["!bang"]);
+        assert_eq!(rule("trail\\  ").segments, vec!["trail\\ "]);
+    }
+
+    #[test]
+    fn test_unanchored_matches_any_level() {
+        let r = rule("*.log");
+        assert!(r.matches("app.log", false));
+        assert!(r.matches("a/b/app.log", false));
+        assert!(!r.matches("app.txt", false));
+    }
+
+    #[test]
+    fn test_anchored_matches_from_base() {
+        let r = rule("/build");
+        assert!(r.matches("build", true));
+        assert!(!r.matches("src/build", true));
+
+        let nested = IgnoreRule::parse("dist", "app").unwrap();
+        assert!(nested.matches("app/dist", true));
+        assert!(!nested.matches("dist", true));
+    }
+
+    #[test]
+    fn test_dir_only_rule() {
+        let r = rule("target/");
+        assert!(r.matches("target", true));
+        assert!(!r.matches("target", false));
+    }
+
+    #[test]
+    fn test_double_star() {
+        assert!(rule("**/gen").matches("a/b/gen", true));
+        assert!(rule("**/gen").matches("gen", true));
+        assert!(rule("logs/**").matches("logs/a/b.txt", false));
+        assert!(!rule("logs/**").matches("logs", true));
+        assert!(rule("a/**/z").matches("a/z", false));
+        assert!(rule("a/**/z").matches("a/b/c/z", false));
+    }
+
+    #[test]
+    fn test_last_rule_wins() {
+        let rules = parse_rules("*.log\n!keep.log\n", "");
+        assert!(is_ignored_by(&rules, "app.log", false));
+        assert!(!is_ignored_by(&rules, "keep.log", false));
+    }
+
+    #[test]
+    fn test_find_walks_up_to_root() {
+        let repo = make_repo();
+        let found = GitIgnore::find(&repo.path().join("app/dist")).unwrap();
+        assert_eq!(found.root, fs::canonicalize(repo.path()).unwrap());
+    }
+
+    #[test]
+    fn test_find_outside_repo() {
+        let dir = TempDir::new().unwrap();
+        let ignore = GitIgnore::new(dir.path().join("elsewhere"));
+        assert!(!ignore.for_dir(dir.path()).is_ignored("x.log", false));
+    }
+
+    #[test]
+    fn test_root_rules_and_exclude() {
+        let repo = make_repo();
+        let ignore = GitIgnore::find(repo.path()).unwrap();
+        let root = ignore.for_dir(repo.path());
+        assert!(root.is_ignored("target", true));
+        assert!(root.is_ignored("debug.log", false));
+        assert!(!root.is_ignored("keep.log", false));
+        assert!(root.is_ignored("scratch.txt", false));
+        assert!(root.is_ignored(".git", true));
+        assert!(!root.is_ignored("app", true));
+    }
+
+    #[test]
+    fn test_nested_ignore_files() {
+        let repo = make_repo();
+        let ignore = GitIgnore::find(repo.path()).unwrap();
+        let app = ignore.for_dir(&repo.path().join("app"));
+        assert!(app.is_ignored("dist", true));
+        assert!(app.is_ignored("cache.tmp", false));
+        assert!(app.is_ignored("server.log", false));
+        assert!(!app.is_ignored("main.rs", false));
+
+        let root = ignore.for_dir(repo.path());
+        assert!(!root.is_ignored("cache.tmp", false));
+    }
+
+    #[test]
+    fn test_everything_inside_ignored_dir() {
+        let repo = make_repo();
+        let ignore = GitIgnore::find(repo.path()).unwrap();
+        let debug = ignore.for_dir(&repo.path().join("target/debug"));
+        assert!(debug.is_ignored("els", false));
+    }
+
+    #[test]
+    fn test_exclude_from_worktree_common_dir() {
+        let dir = TempDir::new().unwrap();
+        let common = dir.path().join("main/.git");
+        let git_dir = common.join("worktrees/wt");
+        let root = dir.path().join("wt");
+        fs::create_dir_all(common.join("info")).unwrap();
+        fs::create_dir_all(&git_dir).unwrap();
+        fs::create_dir_all(&root).unwrap();
+        fs::write(common.join("info/exclude"), "notes.md\n").unwrap();
+        fs::write(git_dir.join("commondir"), "../..\n").unwrap();
+        fs::write(root.join(".git"), format!("gitdir: {}\n", git_dir.display())).unwrap();
+
+        let ignore = GitIgnore::find(&root).unwrap();
+        assert!(ignore.for_dir(&root).is_ignored("notes.md", false));
+    }
+}
@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+
+use crate::filters::glob_match;
+use crate::types::{FileRowInfo, FileType};
+
+const S_ISUID: u32 = 0o4000;
+const S_ISGID: u32 = 0o2000;
+const S_ISVTX: u32 = 0o1000;
+const S_IWOTH: u32 = 0o0002;
+const S_IXALL: u32 = 0o0111;
+
+#[derive(Debug, Clone, Default)]
+pub struct LsColors {
+    types: HashMap<String, String>,
+    patterns: Vec<(String, String)>,
+}
+
+impl LsColors {
+    pub fn parse(spec: &str) -> Self {
+        let mut colors = Self::default();
+
+        for entry in spec.split(':') {
+            let (key, value) = match entry.split_once('=') {
+                Some((k, v)) if !k.is_empty() && !v.is_empty() => (k, v),
+                _ => continue,
+            };
+
+            // `ln=target` means "color as the target"; the default symlink color stands in
+            if value == "target" {
+                continue;
+            }
+
+            // Only file names are colored from LS_COLORS: `no` (other text) and `mi` (the
+            // missing target of a dangling link) come from the theme, and every code is
+            // closed with a plain reset, so `rs` has nothing to override
+            if matches!(key, "no" | "mi" | "rs") {
+                continue;
+            }
+
+            let code = format!("\x1b[{}m", value);
+            if key.starts_with('*') {
+                colors.patterns.push((key.to_lowercase(), code));
+            } else {
+                colors.types.insert(key.to_string(), code);
+            }
+        }
+
+        colors
+    }
+
+    pub fn from_env() -> Option<Self> {
+        let spec = std::env::var("LS_COLORS").ok()?;
+        if spec.is_empty() {
+            return None;
+        }
+        Some(Self::parse(&spec))
+    }
+
+    fn get_type(&self, key: &str) -> Option<&str> {
+        self.types.get(key).map(String::as_str)
+    }
+
+    // Later entries win, matching how dircolors output overrides earlier lines
+    fn get_pattern(&self, name: &str) -> Option<&str> {
+        let name = name.to_lowercase();
+        self.patterns
+            .iter()
+            .rev()
+            .find(|(pattern, _)| glob_match(pattern, &name))
+            .map(|(_, code)| code.as_str())
+    }
+
+    fn get_dir(&self, mode: u32) -> Option<&str> {
+        let sticky = mode & S_ISVTX != 0;
+        let other_writable = mode & S_IWOTH != 0;
+
+        match (sticky, other_writable) {
+            (true, true) => self.get_type("tw"),
+            (false, true) => self.get_type("ow"),
+            (true, false) => self.get_type("st"),
+            _ => None,
+        }
+        .or_else(|| self.get_type("di"))
+    }
+
+    // Special bits come first, then executables, then name patterns, as in GNU ls.
+    // An unset `su`/`sg` falls through to the next applicable key
+    fn get_file(&self, name: &str, mode: u32) -> Option<&str> {
+        let setuid = (mode & S_ISUID != 0).then(|| self.get_type("su")).flatten();
+        let setgid = || (mode & S_ISGID != 0).then(|| self.get_type("sg")).flatten();
+        let exec = || (mode & S_IXALL != 0).then(|| self.get_type("ex")).flatten();
+
+        setuid
+            .or_else(setgid)
+            .or_else(exec)
+            .or_else(|| self.get_pattern(name))
+            .or_else(|| self.get_type("fi"))
+    }
+
+    pub fn get_name_color(&self, info: &FileRowInfo) -> Option<&str> {
+        let mode = info.stat_res.st_mode;
+        let name = info
+            .fname
+            .trim_end_matches('/')
+            .rsplit('/')
+            .next()
+            .unwrap_or_default();
+
+        match info.ftype {
+            FileType::Directory => self.get_dir(mode),
+            FileType::Symlink => self.get_type("ln"),
+            FileType::BrokenSymlink => self.get_type("or").or_else(|| self.get_type("ln")),
+            FileType::Fifo => self.get_type("pi"),
+            FileType::Socket => self.get_type("so"),
+            FileType::BlockDevice => self.get_type("bd"),
+            FileType::CharDevice => self.get_type("cd"),
+            FileType::File => self.get_file(name, mode),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{fixtures, ContentType};
+
+    fn make_test_info(fname: &str, ftype: FileType, mode: u32) -> FileRowInfo {
+        let mut info = fixtures::make_test_info(fname, ftype, ContentType::Text);
+        info.stat_res.st_mode = mode;
+        info
+    }
+
+    #[test]
+    fn test_parse_skips_malformed_entries() {
+        let colors = LsColors::parse("di=01;34::bogus:=5:ln=:*.rs=32");
+        assert_eq!(colors.types.len(), 1);
+        assert_eq!(colors.patterns.len(), 1);
+    }
+
+    #[test]
+    fn test_name_color_types() {
+        let colors = LsColors::parse("di=01;34:ln=01;36:or=31;01:pi=33:so=01;35:bd=40;33:cd=40;33");
+        let cases = [
+            (FileType::Directory, "\x1b[01;34m"),
+            (FileType::Symlink, "\x1b[01;36m"),
+            (FileType::BrokenSymlink, "\x1b[31;01m"),
+            (FileType::Fifo, "\x1b[33m"),
+            (FileType::Socket, "\x1b[01;35m"),
+            (FileType::BlockDevice, "\x1b[40;33m"),
+        ];
+        for (ftype, code) in cases {
+            let info = make_test_info("entry", ftype, 0o755);
+            assert_eq!(colors.get_name_color(&info), Some(code));
+        }
+    }
+
+    #[test]
+    fn test_name_color_orphan_falls_back_to_ln() {
+        let colors = LsColors::parse("ln=36");
+        let info = make_test_info("dead", FileType::BrokenSymlink, 0o777);
+        assert_eq!(colors.get_name_color(&info), Some("\x1b[36m"));
+    }
+
+    #[test]
+    fn test_name_color_extension_case_insensitive() {
+        let colors = LsColors::parse("*.tar=01;31:*.rs=32");
+        let info = make_test_info("dir/Backup.TAR", FileType::File, 0o644);
+        assert_eq!(colors.get_name_color(&info), Some("\x1b[01;31m"));
+    }
+
+    #[test]
+    fn test_name_color_executable_beats_extension() {
+        let colors = LsColors::parse("ex=01;32:*.sh=33");
+        let exec = make_test_info("run.sh", FileType::File, 0o755);
+        let plain = make_test_info("lib.sh", FileType::File, 0o644);
+        assert_eq!(colors.get_name_color(&exec), Some("\x1b[01;32m"));
+        assert_eq!(colors.get_name_color(&plain), Some("\x1b[33m"));
+    }
+
+    #[test]
+    fn test_name_color_setuid_falls_back_to_ex() {
+        let colors = LsColors::parse("ex=01;32:sg=30;43:*.sh=33");
+        let setuid = make_test_info("run.sh", FileType::File, 0o4755);
+        let both = make_test_info("both.sh", FileType::File, 0o6755);
+        let plain = make_test_info("data.sh", FileType::File, 0o4644);
+        assert_eq!(colors.get_name_color(&setuid), Some("\x1b[01;32m"));
+        assert_eq!(colors.get_name_color(&both), Some("\x1b[30;43m"));
+        assert_eq!(colors.get_name_color(&plain), Some("\x1b[33m"));
+    }
+
+    #[test]
+    fn test_parse_ignores_unused_keys() {
+        let colors = LsColors::parse("no=00:mi=01;05;37;41:rs=0:fi=37");
+        assert_eq!(colors.types.len(), 1);
+    }
+
+    #[test]
+    fn test_name_color_sticky_other_writable_dir() {
+        let colors = LsColors::parse("di=34:tw=30;42:ow=34;42");
+        let tmp = make_test_info("tmp", FileType::Directory, 0o1777);
+        let shared = make_test_info("shared", FileType::Directory, 0o777);
+        let home = make_test_info("home", FileType::Directory, 0o755);
+        assert_eq!(colors.get_name_color(&tmp), Some("\x1b[30;42m"));
+        assert_eq!(colors.get_name_color(&shared), Some("\x1b[34;42m"));
+        assert_eq!(colors.get_name_color(&home), Some("\x1b[34m"));
+    }
+
+    #[test]
+    fn test_name_color_unmatched_file() {
+        let colors = LsColors::parse("di=34");
+        let info = make_test_info("notes.txt", FileType::File, 0o644);
+        assert_eq!(colors.get_name_color(&info), None);
+    }
+
+    #[test]
+    fn test_name_color_ln_target_ignored() {
+        let colors = LsColors::parse("ln=target");
+        let info = make_test_info("link", FileType::Symlink, 0o777);
+        assert_eq!(colors.get_name_color(&info), None);
+    }
+}
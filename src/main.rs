@@ -2,24 +2,53 @@ mod colors;
 mod columns;
 mod display;
 mod file_info;
+mod filters;
+mod git;
+mod git_status;
+mod gitignore;
+mod ls_colors;
+mod output;
+mod pager;
+mod parallel;
 mod permissions;
 mod preview;
 mod render;
+mod sort;
+mod terminal;
+mod theme;
+mod tree;
 mod types;
 mod utils;
 
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use columns::{
-    render_col_acls, render_col_filetype, render_col_owner, render_col_preview, render_col_size,
-    render_col_srcname, render_col_targetname, render_col_timeiso,
+    render_col_acls, render_col_filetype, render_col_gitstatus, render_col_owner,
+    render_col_preview, render_col_size, render_col_srcname, render_col_targetname,
+    render_col_timeiso,
 };
-use display::display;
+use display::{display, write_stdout};
 use file_info::get_row_info;
+use filters::{
+    parse_age, parse_hide_list, parse_size, parse_type_filter, NameFilter, RowFilter,
+};
+use git_status::GitRepo;
+use gitignore::{DirIgnore, GitIgnore};
+use ls_colors::LsColors;
+use output::{render_csv, render_json, render_ndjson, render_tsv};
+use parallel::parallel_map;
 use permissions::UserGroupCache;
-use render::render_rows;
-use types::{Args, FileRow, FileType, RenderedCols};
+use render::{get_cols_listing, parse_cols_listing, render_grid, render_rows};
+use sort::{parse_sort_keys, sort_rows};
+use terminal::{get_output_width, use_color};
+use theme::Theme;
+use tree::{flatten_tree, TreeNode};
+use types::{
+    Args, ColType, ColorMode, FileRow, FileRowInfo, FileType, IgnoreMode, InfoNeeds, OutputFormat,
+    RenderOptions, RenderedCols, SizeFormat, SortKey, TimeOptions,
+};
+use utils::{now_epoch, parse_timezone, set_timezone};
 
 fn parse_args() -> Args {
     let mut pargs = pico_args::Arguments::from_env();
@@ -30,6 +59,20 @@ fn parse_args() -> Args {
     }
 
     let full = pargs.contains(["-f", "--full"]);
+    let tree = pargs.contains(["-t", "--tree"]);
+    let grid = pargs.contains(["-C", "--grid"]);
+
+    let depth: Option<usize> = match pargs.opt_value_from_str(["-d", "--depth"]) {
+        Ok(Some(0)) => {
+            eprintln!("Error: --depth must be at least 1");
+            std::process::exit(1);
+        }
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
 
     let filter: Option<String> = match pargs.opt_value_from_str(["-g", "--filter"]) {
         Ok(v) => v,
@@ -39,8 +82,129 @@ fn parse_args() -> Args {
         }
     };
 
+    let format: OutputFormat = match pargs.opt_value_from_str("--format") {
+        Ok(v) => v.unwrap_or(OutputFormat::Text),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let columns: Option<Vec<ColType>> =
+        match pargs.opt_value_from_fn(["-c", "--columns"], parse_cols_listing) {
+            Ok(v) => v,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        };
+
+    let sort: Vec<SortKey> = match pargs.opt_value_from_fn(["-s", "--sort"], parse_sort_keys) {
+        Ok(v) => v.unwrap_or_else(|| vec![SortKey::Name]),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let reverse = pargs.contains(["-r", "--reverse"]);
+    let dirs_first = !pargs.contains("--no-dirs-first");
+    let broken_links = pargs.contains("--broken-links");
+    let no_pager = pargs.contains("--no-pager");
+
+    let case_sensitive = pargs.contains("--case-sensitive");
+    let globs = parse_values(&mut pargs, "--glob");
+    let regexes = parse_values(&mut pargs, "--regex");
+    let exclude_globs = parse_values(&mut pargs, "--exclude-glob");
+    let exclude_regexes = parse_values(&mut pargs, "--exclude-regex");
+
+    let almost_all = pargs.contains(["-A", "--almost-all"]);
+    let hide_dotfiles_flag = pargs.contains("--hide-dotfiles");
+    let hide_flags = parse_values(&mut pargs, "--hide");
+
+    if almost_all && hide_dotfiles_flag {
+        eprintln!("Error: --almost-all cannot be combined with --hide-dotfiles");
+        std::process::exit(1);
+    }
+
+    let (hide_dotfiles, hide) = if almost_all {
+        (false, hide_flags)
+    } else {
+        let env_dotfiles = std::env::var("ELS_HIDE_DOTFILES")
+            .map(|v| !v.is_empty() && v != "0")
+            .unwrap_or(false);
+        let mut hide = std::env::var("ELS_HIDE")
+            .map(|v| parse_hide_list(&v))
+            .unwrap_or_default();
+        hide.extend(hide_flags);
+        (hide_dotfiles_flag || env_dotfiles, hide)
+    };
+
+    let gitignore_flag = pargs.contains("--gitignore");
+    let dim_ignored = pargs.contains("--dim-ignored");
+    let gitignore = match (gitignore_flag, dim_ignored) {
+        (_, true) => Some(IgnoreMode::Dim),
+        (true, false) => Some(IgnoreMode::Hide),
+        (false, false) => None,
+    };
+
+    let larger_than = parse_opt_value(&mut pargs, "--larger-than", parse_size);
+    let smaller_than = parse_opt_value(&mut pargs, "--smaller-than", parse_size);
+    let newer_than = parse_opt_value(&mut pargs, "--newer-than", parse_age);
+    let older_than = parse_opt_value(&mut pargs, "--older-than", parse_age);
+    let owner: Option<String> = parse_opt_value(&mut pargs, "--owner", |s| Ok(s.to_string()));
+    let types = parse_opt_value(&mut pargs, "--type", parse_type_filter).unwrap_or_default();
+
+    let size_format: SizeFormat = match pargs.opt_value_from_str("--size-format") {
+        Ok(v) => v.unwrap_or(SizeFormat::Bytes),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let time_style = parse_opt_value(&mut pargs, "--time-style", |s| s.parse());
+    let time_field = parse_opt_value(&mut pargs, "--time-field", |s| s.parse());
+    let utc = pargs.contains("--utc");
+    let tz: Option<String> = parse_opt_value(&mut pargs, "--tz", parse_timezone);
+
+    if utc && tz.is_some() {
+        eprintln!("Error: --utc cannot be combined with --tz");
+        std::process::exit(1);
+    }
+
+    if let Some(tz) = &tz {
+        set_timezone(tz);
+    }
+
+    let defaults = TimeOptions::default();
+    let time = TimeOptions {
+        style: time_style.unwrap_or(defaults.style),
+        field: time_field.unwrap_or(defaults.field),
+        utc,
+    };
+
+    let theme_spec: Option<String> =
+        parse_opt_value(&mut pargs, "--theme", |s| Ok(s.to_string()))
+            .or_else(|| std::env::var("ELS_THEME").ok().filter(|v| !v.is_empty()));
+    let theme = match Theme::load(theme_spec.as_deref()) {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let color: ColorMode = match pargs.opt_value_from_str("--color") {
+        Ok(v) => v.unwrap_or(ColorMode::Auto),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
     let remaining = pargs.finish();
-    let mut start_path = String::from("./");
+    let mut start_paths = Vec::new();
 
     for arg in &remaining {
         let s = arg.to_string_lossy();
@@ -48,40 +212,161 @@ fn parse_args() -> Args {
             eprintln!("Unknown argument: {}", s);
             std::process::exit(1);
         }
-        if start_path == "./" {
-            start_path = s.into_owned();
-        }
+        start_paths.push(s.into_owned());
+    }
+
+    if start_paths.is_empty() {
+        start_paths.push(String::from("./"));
     }
 
-    Args {
-        start_path,
+    let mut args = Args {
+        start_paths,
         filter,
         full,
+        tree: tree || depth.is_some(),
+        depth,
+        grid,
+        format,
+        columns,
+        sort,
+        reverse,
+        dirs_first,
+        broken_links,
+        globs,
+        regexes,
+        exclude_globs,
+        exclude_regexes,
+        case_sensitive,
+        hide_dotfiles,
+        hide,
+        names: NameFilter::default(),
+        gitignore,
+        larger_than,
+        smaller_than,
+        newer_than,
+        older_than,
+        owner,
+        types,
+        size_format,
+        time,
+        theme,
+        color,
+        no_pager,
+    };
+
+    if args.grid && args.tree {
+        eprintln!("Error: --grid cannot be combined with --tree or --depth");
+        std::process::exit(1);
+    }
+
+    if args.grid && args.format != OutputFormat::Text {
+        eprintln!("Error: --grid only applies to text output");
+        std::process::exit(1);
+    }
+
+    args.names = match NameFilter::new(&args) {
+        Ok(names) => names,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    args
+}
+
+fn parse_opt_value<T>(
+    pargs: &mut pico_args::Arguments,
+    key: &'static str,
+    f: fn(&str) -> Result<T, String>,
+) -> Option<T> {
+    match pargs.opt_value_from_fn(key, f) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn parse_values(pargs: &mut pico_args::Arguments, key: &'static str) -> Vec<String> {
+    match pargs.values_from_str(key) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
     }
 }
 
 fn print_help() {
     println!("rust-ls - Enhanced directory listing utility");
     println!();
-    println!("Usage: rust-ls [OPTIONS] [STARTPATH]");
+    println!("Usage: rust-ls [OPTIONS] [PATH]...");
     println!();
     println!("Arguments:");
-    println!("  STARTPATH    Directory path to list (default: './')");
+    println!("  PATH         Directories to list and files to show (default: './')");
     println!();
     println!("Options:");
     println!("  -f, --full       Enable full output mode");
     println!("  -g, --filter     Filter results by substring");
+    println!("  -t, --tree       List subdirectories recursively as a tree");
+    println!("  -C, --grid       Show names only, in as many columns as fit (like ls -C)");
+    println!("  -d, --depth N    Limit tree listing to N levels (implies --tree)");
+    println!("  -c, --columns    Comma-separated columns to show, in order");
+    println!("                   (acls, owner, filetype, size, time, git, name, target, preview)");
+    println!("  -s, --sort KEYS  Comma-separated sort keys, applied in order");
+    println!("                   (name, size, time, ext, type, owner; default: name)");
+    println!("  -r, --reverse    Reverse the sort order");
+    println!("  --no-dirs-first  Sort directories together with files");
+    println!("  --glob PATTERN   Only list names matching a glob (repeatable)");
+    println!("  --regex PATTERN  Only list names matching a regex (repeatable)");
+    println!("  --exclude-glob PATTERN   Hide names matching a glob (repeatable)");
+    println!("  --exclude-regex PATTERN  Hide names matching a regex (repeatable)");
+    println!("  --case-sensitive Match --filter, globs and regexes case-sensitively");
+    println!("  --hide-dotfiles  Hide entries whose names start with '.'");
+    println!("                   (default when $ELS_HIDE_DOTFILES is set)");
+    println!("  --hide PATTERN   Hide names matching a glob inside listed directories");
+    println!("                   (repeatable; adds to the colon-separated $ELS_HIDE)");
+    println!("  -A, --almost-all Show dotfiles and $ELS_HIDE names (the default)");
+    println!("  --gitignore      Hide entries matched by .gitignore, .ignore and");
+    println!("                   .git/info/exclude rules of the enclosing repository");
+    println!("  --dim-ignored    Like --gitignore, but show ignored entries dimmed");
+    println!("  --larger-than SIZE   Only list entries larger than SIZE (e.g. 10M)");
+    println!("  --smaller-than SIZE  Only list entries smaller than SIZE");
+    println!("  --newer-than AGE     Only list entries modified within AGE (e.g. 2d, 5h)");
+    println!("  --older-than AGE     Only list entries modified before AGE ago");
+    println!("  --owner USER     Only list entries owned by USER (name or uid)");
+    println!("  --type TYPES     Only list entries of these types: d (dir), f (file),");
+    println!("                   l (symlink), e (executable), t (text), b (binary)");
+    println!("  --broken-links   Only list symlinks whose target does not resolve");
+    println!("  --size-format F  Size display: bytes, human, si, iec, blocks (default: bytes)");
+    println!("  --time-style S   Time display: long-iso, iso, rfc3339, relative, epoch,");
+    println!("                   or +FORMAT");
+    println!("                   (strftime, with %N for nanoseconds; default: long-iso)");
+    println!("  --time-field F   Time to show: mtime, atime, ctime, btime (default: mtime)");
+    println!("  --utc            Show times in UTC");
+    println!("  --tz ZONE        Show times in the given timezone (e.g. America/New_York)");
+    println!("  --theme THEME    Color theme: dark, light, or a theme file path");
+    println!("                   (default: $ELS_THEME, ~/.config/els/theme, then dark)");
+    println!("  --color WHEN     Colorize output: auto, always, never (default: auto;");
+    println!("                   honors NO_COLOR and CLICOLOR_FORCE)");
+    println!("  --no-pager       Print directly instead of paging long output");
+    println!("                   (pager: $ELS_PAGER, then $PAGER, then built-in)");
+    println!("  --format FMT     Output format: text, json, ndjson, csv, tsv");
     println!("  -h, --help       Show this help message");
 }
 
-fn get_dir_listing(start: &str, filter: Option<&str>) -> Option<Vec<String>> {
-    let path = Path::new(start);
-
-    if !path.exists() || !path.is_dir() {
-        return None;
-    }
-
-    let entries = fs::read_dir(path).ok()?;
+// Each path comes with its type from readdir and whether the ignore rules match it
+// With `keep_dirs`, directories skip the name filter so a tree can still reach matches below them
+fn get_dir_listing(
+    start: &str,
+    filter: &NameFilter,
+    ignore: &DirIgnore,
+    keep_dirs: bool,
+) -> Option<Vec<(String, Option<fs::FileType>, bool)>> {
+    // read_dir fails on anything that is not a directory, so no stat is needed up front
+    let entries = fs::read_dir(start).ok()?;
 
     let trimmed = start.trim_end_matches('/');
     let real_start = if trimmed.starts_with("./") && trimmed.len() > 2 {
@@ -92,19 +377,20 @@ fn get_dir_listing(start: &str, filter: Option<&str>) -> Option<Vec<String>> {
         trimmed
     };
 
-    let paths: Vec<String> = entries
+    let paths: Vec<(String, Option<fs::FileType>, bool)> = entries
         .filter_map(|e| e.ok())
         .filter_map(|e| {
             let name = e.file_name().to_string_lossy().to_string();
-            if let Some(f) = filter {
-                if !name.to_lowercase().contains(&f.to_lowercase()) {
-                    return None;
-                }
+            let entry_type = e.file_type().ok();
+            let is_dir = entry_type.is_some_and(|t| t.is_dir());
+            if filter.is_hidden(&name) || !((keep_dirs && is_dir) || filter.matches(&name)) {
+                return None;
             }
+            let ignored = ignore.is_ignored(&name, is_dir);
             if real_start.is_empty() {
-                Some(name)
+                Some((name, entry_type, ignored))
             } else {
-                Some(format!("{}/{}", real_start, name))
+                Some((format!("{}/{}", real_start, name), entry_type, ignored))
             }
         })
         .collect();
@@ -112,77 +398,373 @@ fn get_dir_listing(start: &str, filter: Option<&str>) -> Option<Vec<String>> {
     Some(paths)
 }
 
-fn build_row(fname: &str, cache: &UserGroupCache, full: bool) -> Option<FileRow> {
-    let info = get_row_info(fname)?;
+fn get_active_cols(args: &Args) -> Vec<ColType> {
+    if args.grid {
+        return vec![ColType::SrcName];
+    }
+
+    match &args.columns {
+        Some(cols) => cols.clone(),
+        None => get_cols_listing(args.full),
+    }
+}
+
+// Colors may key on mode bits through LS_COLORS, so they count as needing a stat
+fn get_info_needs(args: &Args, cols: &[ColType]) -> InfoNeeds {
+    let json = matches!(args.format, OutputFormat::Json | OutputFormat::Ndjson);
+    let content = json
+        || cols.contains(&ColType::FileType)
+        || cols.contains(&ColType::Preview)
+        || args.types.iter().any(|t| matches!(t, 'e' | 't' | 'b'))
+        || args.sort.contains(&SortKey::Type);
+    let stat_cols = [ColType::Acls, ColType::Owner, ColType::Size, ColType::TimeIso];
+    let stat_sort = [SortKey::Size, SortKey::Time, SortKey::Owner];
+    let stat = content
+        || stat_cols.iter().any(|col| cols.contains(col))
+        || stat_sort.iter().any(|key| args.sort.contains(key))
+        || args.larger_than.is_some()
+        || args.smaller_than.is_some()
+        || args.newer_than.is_some()
+        || args.older_than.is_some()
+        || args.owner.is_some()
+        || use_color(args.color);
+
+    let link_target = json || cols.contains(&ColType::TargetName);
+    // Broken links show and filter differently, and links to directories sort with them
+    let link_stat = stat
+        || link_target
+        || args.dirs_first
+        || args.broken_links
+        || args.types.contains(&'d');
+
+    InfoNeeds {
+        stat,
+        link_stat,
+        link_target,
+        content,
+    }
+}
+
+// `entry_type` comes from readdir and spares the stat when nothing shown needs it
+fn build_row(fname: &str, entry_type: Option<fs::FileType>, ctx: &ListContext) -> Option<FileRow> {
+    let info = get_row_info(fname, ctx.needs, entry_type)?;
+    let args = ctx.args;
+    let cache = &ctx.cache;
+    let wants = |col: ColType| ctx.cols.contains(&col);
+    let blank = || String::from(" ");
 
     let render = RenderedCols {
-        acls: if full { render_col_acls(&info) } else { String::from(" ") },
-        owner: if full { render_col_owner(&info, cache) } else { String::from(" ") },
-        filetype: if full { render_col_filetype(&info) } else { String::from(" ") },
-        size: render_col_size(&info),
-        timeiso: render_col_timeiso(&info),
+        acls: if wants(ColType::Acls) { render_col_acls(&info) } else { blank() },
+        owner: if wants(ColType::Owner) { render_col_owner(&info, cache) } else { blank() },
+        filetype: if wants(ColType::FileType) { render_col_filetype(&info) } else { blank() },
+        size: if wants(ColType::Size) { render_col_size(&info, args.size_format) } else { blank() },
+        timeiso: if wants(ColType::TimeIso) {
+            render_col_timeiso(&info, &args.time, ctx.now)
+        } else {
+            blank()
+        },
+        gitstatus: if wants(ColType::GitStatus) {
+            render_col_gitstatus(&info, ctx.git.as_ref())
+        } else {
+            blank()
+        },
         srcname: render_col_srcname(&info),
-        targetname: render_col_targetname(&info),
-        preview: if full { render_col_preview(&info) } else { String::from(" ") },
+        targetname: if wants(ColType::TargetName) { render_col_targetname(&info) } else { blank() },
+        preview: if wants(ColType::Preview) { render_col_preview(&info) } else { blank() },
+        tree: String::new(),
+        dimmed: false,
     };
 
     Some(FileRow { info, render })
 }
 
-fn sort_rows(rows: &mut [FileRow]) {
-    rows.sort_by(|a, b| {
-        let a_is_dir = a.info.ftype == FileType::Directory;
-        let b_is_dir = b.info.ftype == FileType::Directory;
-
-        match (a_is_dir, b_is_dir) {
-            (true, false) => std::cmp::Ordering::Less,
-            (false, true) => std::cmp::Ordering::Greater,
-            _ => {
-                let a_name = a.info.fname.to_lowercase();
-                let b_name = b.info.fname.to_lowercase();
-                a_name.cmp(&b_name)
-            }
+struct ListContext<'a> {
+    args: &'a Args,
+    cache: UserGroupCache,
+    rows: RowFilter,
+    cols: Vec<ColType>,
+    needs: InfoNeeds,
+    now: i64,
+    ignore: Option<GitIgnore>,
+    git: Option<GitRepo>,
+}
+
+impl<'a> ListContext<'a> {
+    fn new(args: &'a Args) -> Self {
+        let now = now_epoch();
+        let cols = get_active_cols(args);
+        Self {
+            args,
+            cache: UserGroupCache::new(),
+            rows: RowFilter::new(args, now),
+            needs: get_info_needs(args, &cols),
+            cols,
+            now,
+            ignore: None,
+            git: None,
         }
-    });
+    }
 }
 
-fn get_files(start: &str, full: bool, filter: Option<&str>) -> Option<Vec<FileRow>> {
-    let paths = get_dir_listing(start, filter)?;
-    let cache = UserGroupCache::new();
+fn get_rows(start: &str, ctx: &ListContext) -> Option<Vec<FileRow>> {
+    let ignore = match &ctx.ignore {
+        Some(gitignore) => gitignore.for_dir(Path::new(start)),
+        None => DirIgnore::default(),
+    };
+    let paths = get_dir_listing(start, &ctx.args.names, &ignore, ctx.args.tree)?;
+    let hide_ignored = ctx.args.gitignore == Some(IgnoreMode::Hide);
 
-    let mut rows: Vec<FileRow> = paths
+    // Hidden entries go before any stat, so an ignored `node_modules` costs one readdir entry
+    let visible: Vec<&(String, Option<fs::FileType>, bool)> = paths
         .iter()
-        .filter_map(|p| build_row(p, &cache, full))
+        .filter(|(_, _, ignored)| !(hide_ignored && *ignored))
         .collect();
 
-    sort_rows(&mut rows);
+    let mut rows: Vec<FileRow> = parallel_map(&visible, |(p, entry_type, ignored)| {
+        let mut row = build_row(p, *entry_type, ctx)?;
+        row.render.dimmed = *ignored;
+        Some(row)
+    })
+    .into_iter()
+    .flatten()
+    .collect();
+
+    sort_rows(&mut rows, ctx.args, &ctx.cache);
     Some(rows)
 }
 
-fn render_error() {
-    eprintln!("Path could not be found, or path is not a directory.");
+fn should_descend(info: &FileRowInfo, args: &Args, level: usize) -> bool {
+    // Symlinked directories are never followed, which also rules out cycles
+    info.ftype == FileType::Directory && args.depth.is_none_or(|max| level < max)
 }
 
-fn run(start: &str, full: bool, filter: Option<&str>) -> bool {
-    let files = match get_files(start, full, filter) {
-        Some(f) => f,
-        None => {
-            render_error();
-            return false;
+fn get_tree(start: &str, ctx: &ListContext, level: usize) -> Option<Vec<TreeNode>> {
+    let rows = get_rows(start, ctx)?;
+
+    // Directories without a matching row of their own stay when a descendant matches
+    let nodes = rows
+        .into_iter()
+        .filter_map(|row| {
+            let mut node = TreeNode::new(row);
+            if should_descend(&node.row.info, ctx.args, level) {
+                node.children =
+                    get_tree(&node.row.info.fname, ctx, level + 1).unwrap_or_default();
+            }
+            let info = &node.row.info;
+            let name = Path::new(&info.fname).file_name().unwrap_or_default();
+            let matches = ctx.args.names.matches(&name.to_string_lossy())
+                && ctx.rows.matches(info, &ctx.cache);
+            if matches || !node.children.is_empty() {
+                Some(node)
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    Some(nodes)
+}
+
+fn get_files(start: &str, args: &Args) -> Option<Vec<FileRow>> {
+    let mut ctx = ListContext::new(args);
+    // Each start path may sit in a different repository
+    ctx.ignore = args.gitignore.and_then(|_| GitIgnore::find(Path::new(start)));
+    if ctx.cols.contains(&ColType::GitStatus) {
+        ctx.git = GitRepo::open(Path::new(start));
+    }
+
+    if !args.tree {
+        let mut rows = get_rows(start, &ctx)?;
+        rows.retain(|row| ctx.rows.matches(&row.info, &ctx.cache));
+        return Some(rows);
+    }
+
+    let nodes = get_tree(start, &ctx, 1)?;
+    let mut rows = Vec::new();
+    flatten_tree(nodes, "", &mut rows);
+    Some(rows)
+}
+
+struct Section {
+    header: Option<String>,
+    rows: Vec<FileRow>,
+}
+
+// Operands usually share a directory, so its rules are kept until one from elsewhere comes up
+fn is_operand_ignored(path: &str, parent_ignore: &mut Option<(PathBuf, DirIgnore)>) -> bool {
+    let parent = match Path::new(path).parent() {
+        Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+
+    if parent_ignore.as_ref().is_none_or(|(dir, _)| *dir != parent) {
+        let ignore = GitIgnore::find(&parent)
+            .map(|g| g.for_dir(&parent))
+            .unwrap_or_default();
+        *parent_ignore = Some((parent, ignore));
+    }
+
+    let name = Path::new(path).file_name().unwrap_or_default();
+    parent_ignore
+        .as_ref()
+        .is_some_and(|(_, ignore)| ignore.is_ignored(&name.to_string_lossy(), false))
+}
+
+// File operands keep the path they were given, so `els src/*.rs` shows where each one lives
+fn get_file_operand_rows(paths: &[&str], args: &Args) -> Option<Vec<FileRow>> {
+    let mut ctx = ListContext::new(args);
+    let wants_git = ctx.cols.contains(&ColType::GitStatus);
+    let mut rows: Vec<FileRow> = Vec::new();
+    let mut parent_ignore: Option<(PathBuf, DirIgnore)> = None;
+
+    for path in paths {
+        let ignored = args.gitignore.is_some() && is_operand_ignored(path, &mut parent_ignore);
+        if ignored && args.gitignore == Some(IgnoreMode::Hide) {
+            continue;
+        }
+
+        // Operands may come from several repositories; reopen only when leaving the current one
+        if wants_git && !ctx.git.as_ref().is_some_and(|g| g.contains(Path::new(path))) {
+            ctx.git = GitRepo::open(Path::new(path));
+        }
+
+        let mut row = match build_row(path, None, &ctx) {
+            Some(row) if ctx.rows.matches(&row.info, &ctx.cache) => row,
+            _ => continue,
+        };
+        let parent = Path::new(&row.info.fname).parent();
+        if let Some(parent) = parent.filter(|p| !p.as_os_str().is_empty()) {
+            row.render.srcname = format!("{}/{}", parent.display(), row.render.srcname);
+        }
+        row.render.dimmed = ignored;
+        rows.push(row);
+    }
+
+    sort_rows(&mut rows, args, &ctx.cache);
+    Some(rows)
+}
+
+fn render_error(path: &str, err: &std::io::Error) {
+    eprintln!("Error: cannot access '{}': {}", path, err);
+}
+
+// Like `ls`: named files first as one group, then each directory under its own header.
+// The bool is false when any path could not be listed.
+fn get_sections(args: &Args) -> (Vec<Section>, bool) {
+    let mut ok = true;
+    let mut files: Vec<&str> = Vec::new();
+    let mut dirs: Vec<&str> = Vec::new();
+
+    for path in &args.start_paths {
+        if Path::new(path).is_dir() {
+            dirs.push(path);
+            continue;
         }
+
+        match fs::symlink_metadata(path) {
+            Ok(_) => files.push(path),
+            Err(e) => {
+                render_error(path, &e);
+                ok = false;
+            }
+        }
+    }
+
+    let show_headers = args.start_paths.len() > 1;
+    let mut sections = Vec::new();
+
+    if !files.is_empty() {
+        match get_file_operand_rows(&files, args) {
+            Some(rows) => sections.push(Section { header: None, rows }),
+            None => ok = false,
+        }
+    }
+
+    for dir in dirs {
+        match get_files(dir, args) {
+            Some(rows) => sections.push(Section {
+                header: show_headers.then(|| dir.to_string()),
+                rows,
+            }),
+            None => {
+                eprintln!("Error: cannot open directory '{}'", dir);
+                ok = false;
+            }
+        }
+    }
+
+    (sections, ok)
+}
+
+fn render_sections(sections: &[Section], render: impl Fn(&[FileRow]) -> String) -> String {
+    let rendered: Vec<String> = sections
+        .iter()
+        .filter_map(|section| {
+            let body = render(&section.rows);
+            // File operands that all filtered out leave no blank block before the directories
+            match (&section.header, body.is_empty()) {
+                (Some(header), true) => Some(format!("{}:", header)),
+                (Some(header), false) => Some(format!("{}:\n{}", header, body)),
+                (None, true) => None,
+                (None, false) => Some(body),
+            }
+        })
+        .collect();
+
+    rendered.join("\n\n")
+}
+
+// Returns the process exit code, which is the pager's when one ran
+fn run(args: &Args) -> i32 {
+    let (sections, ok) = get_sections(args);
+    if sections.is_empty() {
+        return 1;
+    }
+    let status = if ok { 0 } else { 1 };
+
+    let cols = get_active_cols(args);
+    let opts = RenderOptions {
+        time: args.time.clone(),
+        now: now_epoch(),
+        ls_colors: LsColors::from_env(),
+        theme: args.theme.clone(),
+        color: use_color(args.color),
+        width: get_output_width(),
     };
 
-    let rows = render_rows(&files, full);
-    display(&rows);
-    true
+    if args.format == OutputFormat::Text {
+        // Like `ls -C`, grids lay out for 80 columns when there is no terminal to measure
+        let output = if args.grid {
+            render_sections(&sections, |rows| {
+                render_grid(rows, &opts, opts.width.unwrap_or(80))
+            })
+        } else {
+            render_sections(&sections, |rows| render_rows(rows, &cols, &opts))
+        };
+        let pager_status = display(&output, args.no_pager);
+        return if pager_status != 0 { pager_status } else { status };
+    }
+
+    // Structured formats get one flat list; each row's fname already carries its directory
+    let files: Vec<FileRow> = sections.into_iter().flat_map(|s| s.rows).collect();
+
+    match args.format {
+        OutputFormat::Json => write_stdout(&render_json(&files)),
+        OutputFormat::Ndjson => write_stdout(&render_ndjson(&files)),
+        OutputFormat::Csv => write_stdout(&render_csv(&files, &cols)),
+        OutputFormat::Tsv => write_stdout(&render_tsv(&files, &cols)),
+        OutputFormat::Text => {}
+    }
+
+    status
 }
 
 fn main() {
     let args = parse_args();
-    let success = run(&args.start_path, args.full, args.filter.as_deref());
+    let code = run(&args);
 
-    if !success {
-        std::process::exit(1);
+    if code != 0 {
+        std::process::exit(code);
     }
 }
 
@@ -192,75 +774,25 @@ mod tests {
     use tempfile::TempDir;
     use std::fs::File;
     use std::io::Write;
+    use std::os::unix::fs::symlink;
 
-    fn make_test_row(fname: &str, ftype: FileType) -> FileRow {
-        use types::{ContentType, FileRowInfo, StatResult};
-
-        let info = FileRowInfo {
-            fname: String::from(fname),
-            ftype,
-            stat_res: StatResult {
-                st_mode: 0o644,
-                st_mtime: 1704067200,
-                st_uid: 1000,
-                st_gid: 1000,
-                st_size: 1024,
-            },
-            content_type: ContentType::Text,
-            time_epoch: String::from("1704067200"),
+    fn make_substring_filter(sub: &str) -> NameFilter {
+        let args = Args {
+            filter: Some(String::from(sub)),
+            ..Args::default()
         };
-        FileRow {
-            info,
-            render: RenderedCols::default(),
-        }
+        NameFilter::new(&args).unwrap()
     }
 
-    #[test]
-    fn test_sort_rows_dirs_first() {
-        let mut rows = vec![
-            make_test_row("file.txt", FileType::File),
-            make_test_row("dir", FileType::Directory),
-        ];
-        sort_rows(&mut rows);
-        assert_eq!(rows[0].info.ftype, FileType::Directory);
-        assert_eq!(rows[1].info.ftype, FileType::File);
+    // parse_args compiles the name filter last; tests setting pattern fields do the same
+    fn with_names(mut args: Args) -> Args {
+        args.names = NameFilter::new(&args).unwrap();
+        args
     }
 
-    #[test]
-    fn test_sort_rows_alphabetical() {
-        let mut rows = vec![
-            make_test_row("zebra", FileType::File),
-            make_test_row("apple", FileType::File),
-            make_test_row("mango", FileType::File),
-        ];
-        sort_rows(&mut rows);
-        assert!(rows[0].info.fname.contains("apple"));
-        assert!(rows[1].info.fname.contains("mango"));
-        assert!(rows[2].info.fname.contains("zebra"));
-    }
-
-    #[test]
-    fn test_sort_rows_case_insensitive() {
-        let mut rows = vec![
-            make_test_row("Zebra", FileType::File),
-            make_test_row("apple", FileType::File),
-        ];
-        sort_rows(&mut rows);
-        assert!(rows[0].info.fname.contains("apple"));
-        assert!(rows[1].info.fname.contains("Zebra"));
-    }
-
-    #[test]
-    fn test_sort_rows_dirs_alphabetical() {
-        let mut rows = vec![
-            make_test_row("zdir", FileType::Directory),
-            make_test_row("adir", FileType::Directory),
-            make_test_row("file", FileType::File),
-        ];
-        sort_rows(&mut rows);
-        assert!(rows[0].info.fname.contains("adir"));
-        assert!(rows[1].info.fname.contains("zdir"));
-        assert!(rows[2].info.fname.contains("file"));
+    fn list_names(start: &str, filter: &NameFilter) -> Option<Vec<String>> {
+        let entries = get_dir_listing(start, filter, &DirIgnore::default(), false)?;
+        Some(entries.into_iter().map(|(path, _, _)| path).collect())
     }
 
     #[test]
@@ -269,14 +801,14 @@ mod tests {
         File::create(dir.path().join("file1.txt")).unwrap();
         File::create(dir.path().join("file2.txt")).unwrap();
 
-        let result = get_dir_listing(dir.path().to_str().unwrap(), None);
+        let result = list_names(dir.path().to_str().unwrap(), &NameFilter::default());
         assert!(result.is_some());
         assert_eq!(result.unwrap().len(), 2);
     }
 
     #[test]
     fn test_get_dir_listing_nonexistent() {
-        let result = get_dir_listing("/nonexistent/path/12345", None);
+        let result = list_names("/nonexistent/path/12345", &NameFilter::default());
         assert!(result.is_none());
     }
 
@@ -286,7 +818,7 @@ mod tests {
         File::create(dir.path().join("test.txt")).unwrap();
         File::create(dir.path().join("other.log")).unwrap();
 
-        let result = get_dir_listing(dir.path().to_str().unwrap(), Some("test"));
+        let result = list_names(dir.path().to_str().unwrap(), &make_substring_filter("test"));
         assert!(result.is_some());
         assert_eq!(result.unwrap().len(), 1);
     }
@@ -297,24 +829,480 @@ mod tests {
         File::create(dir.path().join("TEST.txt")).unwrap();
         File::create(dir.path().join("other.log")).unwrap();
 
-        let result = get_dir_listing(dir.path().to_str().unwrap(), Some("test"));
+        let result = list_names(dir.path().to_str().unwrap(), &make_substring_filter("test"));
         assert!(result.is_some());
         assert_eq!(result.unwrap().len(), 1);
     }
 
+    #[test]
+    fn test_get_dir_listing_with_glob() {
+        let dir = TempDir::new().unwrap();
+        File::create(dir.path().join("app.log")).unwrap();
+        File::create(dir.path().join("app.txt")).unwrap();
+        File::create(dir.path().join("db.log")).unwrap();
+
+        let args = Args {
+            globs: vec![String::from("*.log")],
+            ..Args::default()
+        };
+        let filter = NameFilter::new(&args).unwrap();
+        let result = list_names(dir.path().to_str().unwrap(), &filter).unwrap();
+        assert_eq!(result.len(), 2);
+        assert!(result.iter().all(|p| p.ends_with(".log")));
+    }
+
+    #[test]
+    fn test_get_dir_listing_hide_dotfiles() {
+        let dir = TempDir::new().unwrap();
+        File::create(dir.path().join(".bashrc")).unwrap();
+        File::create(dir.path().join("notes.txt")).unwrap();
+
+        let args = Args {
+            hide_dotfiles: true,
+            ..Args::default()
+        };
+        let filter = NameFilter::new(&args).unwrap();
+        let result = list_names(dir.path().to_str().unwrap(), &filter).unwrap();
+        assert_eq!(result.len(), 1);
+        assert!(result[0].ends_with("notes.txt"));
+    }
+
+    #[test]
+    fn test_get_dir_listing_hide_patterns() {
+        let dir = TempDir::new().unwrap();
+        File::create(dir.path().join(".DS_Store")).unwrap();
+        File::create(dir.path().join("mod.pyc")).unwrap();
+        File::create(dir.path().join("mod.py")).unwrap();
+
+        let args = Args {
+            hide: vec![String::from(".DS_Store"), String::from("*.pyc")],
+            ..Args::default()
+        };
+        let filter = NameFilter::new(&args).unwrap();
+        let result = list_names(dir.path().to_str().unwrap(), &filter).unwrap();
+        assert_eq!(result.len(), 1);
+        assert!(result[0].ends_with("mod.py"));
+    }
+
     #[test]
     fn test_get_files_valid() {
         let dir = TempDir::new().unwrap();
         let mut file = File::create(dir.path().join("test.txt")).unwrap();
         writeln!(file, "Hello").unwrap();
 
-        let result = get_files(dir.path().to_str().unwrap(), false, None);
+        let result = get_files(dir.path().to_str().unwrap(), &Args::default());
         assert!(result.is_some());
     }
 
     #[test]
     fn test_get_files_nonexistent() {
-        let result = get_files("/nonexistent/path/12345", false, None);
+        let result = get_files("/nonexistent/path/12345", &Args::default());
         assert!(result.is_none());
     }
+
+    fn make_operands_dir() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        File::create(dir.path().join("sub/inner.txt")).unwrap();
+        File::create(dir.path().join("b.log")).unwrap();
+        File::create(dir.path().join("a.log")).unwrap();
+        dir
+    }
+
+    fn path_in(dir: &TempDir, name: &str) -> String {
+        dir.path().join(name).to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_get_sections_single_dir_no_header() {
+        let dir = make_operands_dir();
+        let args = Args {
+            start_paths: vec![path_in(&dir, "sub")],
+            ..Args::default()
+        };
+        let (sections, ok) = get_sections(&args);
+        assert!(ok);
+        assert_eq!(sections.len(), 1);
+        assert!(sections[0].header.is_none());
+    }
+
+    #[test]
+    fn test_get_sections_files_then_dirs() {
+        let dir = make_operands_dir();
+        let sub = path_in(&dir, "sub");
+        let args = Args {
+            start_paths: vec![sub.clone(), path_in(&dir, "b.log"), path_in(&dir, "a.log")],
+            ..Args::default()
+        };
+        let (sections, ok) = get_sections(&args);
+        assert!(ok);
+        assert_eq!(sections.len(), 2);
+        assert!(sections[0].header.is_none());
+        assert!(sections[0].rows[0].render.srcname.ends_with("/a.log"));
+        assert!(sections[0].rows[1].render.srcname.ends_with("/b.log"));
+        assert_eq!(sections[1].header, Some(sub));
+        assert_eq!(sections[1].rows[0].render.srcname, "inner.txt");
+    }
+
+    #[test]
+    fn test_get_sections_missing_path_reported() {
+        let dir = make_operands_dir();
+        let args = Args {
+            start_paths: vec![path_in(&dir, "nope"), path_in(&dir, "a.log")],
+            ..Args::default()
+        };
+        let (sections, ok) = get_sections(&args);
+        assert!(!ok);
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].rows.len(), 1);
+    }
+
+    #[test]
+    fn test_get_sections_globs_spare_file_operands() {
+        let dir = make_operands_dir();
+        let args = with_names(Args {
+            start_paths: vec![path_in(&dir, "a.log"), path_in(&dir, "sub/inner.txt")],
+            globs: vec![String::from("*.txt")],
+            ..Args::default()
+        });
+        let (sections, _) = get_sections(&args);
+        assert_eq!(sections[0].rows.len(), 2);
+    }
+
+    #[test]
+    fn test_get_sections_hide_spares_file_operands() {
+        let dir = make_operands_dir();
+        let args = with_names(Args {
+            start_paths: vec![path_in(&dir, "a.log")],
+            hide: vec![String::from("*.log")],
+            ..Args::default()
+        });
+        let (sections, _) = get_sections(&args);
+        assert_eq!(sections[0].rows.len(), 1);
+    }
+
+    #[test]
+    fn test_render_sections_headers() {
+        let sections = [
+            Section {
+                header: None,
+                rows: Vec::new(),
+            },
+            Section {
+                header: Some(String::from("dir")),
+                rows: Vec::new(),
+            },
+        ];
+        let result = render_sections(&sections, |_| String::from("rows"));
+        assert_eq!(result, "rows\n\ndir:\nrows");
+        let empty = render_sections(&sections[1..], |_| String::new());
+        assert_eq!(empty, "dir:");
+        let filtered = render_sections(&sections, |_| String::new());
+        assert_eq!(filtered, "dir:");
+    }
+
+    #[test]
+    fn test_get_active_cols_default() {
+        assert_eq!(get_active_cols(&Args::default()), get_cols_listing(false));
+        let args = Args {
+            full: true,
+            ..Args::default()
+        };
+        assert_eq!(get_active_cols(&args), get_cols_listing(true));
+    }
+
+    #[test]
+    fn test_get_active_cols_explicit() {
+        let args = Args {
+            columns: Some(vec![ColType::Owner, ColType::SrcName]),
+            ..Args::default()
+        };
+        assert_eq!(get_active_cols(&args), [ColType::Owner, ColType::SrcName]);
+    }
+
+    #[test]
+    fn test_build_row_skips_unselected_cols() {
+        let dir = TempDir::new().unwrap();
+        let file_path = dir.path().join("test.txt");
+        File::create(&file_path).unwrap();
+
+        let args = Args {
+            columns: Some(vec![ColType::Owner, ColType::SrcName]),
+            ..Args::default()
+        };
+        let ctx = ListContext::new(&args);
+        let row = build_row(file_path.to_str().unwrap(), None, &ctx).unwrap();
+        assert!(row.render.owner.contains(':'));
+        assert_eq!(row.render.acls, " ");
+        assert_eq!(row.render.preview, " ");
+    }
+
+    #[test]
+    fn test_get_info_needs() {
+        let names = Args {
+            columns: Some(vec![ColType::SrcName]),
+            color: ColorMode::Never,
+            dirs_first: false,
+            ..Args::default()
+        };
+        assert_eq!(get_info_needs(&names, &get_active_cols(&names)), InfoNeeds::default());
+
+        let grouped = Args {
+            dirs_first: true,
+            ..names.clone()
+        };
+        let needs = get_info_needs(&grouped, &get_active_cols(&grouped));
+        assert!(needs.link_stat && !needs.stat);
+
+        let targets = Args {
+            columns: Some(vec![ColType::SrcName, ColType::TargetName]),
+            ..names.clone()
+        };
+        let needs = get_info_needs(&targets, &get_active_cols(&targets));
+        assert!(needs.link_target && needs.link_stat && !needs.stat && !needs.content);
+
+        let typed = Args {
+            types: vec!['t'],
+            ..names.clone()
+        };
+        let needs = get_info_needs(&typed, &get_active_cols(&typed));
+        assert!(needs.stat && needs.content);
+
+        let sized = Args {
+            sort: vec![SortKey::Size],
+            ..names.clone()
+        };
+        let needs = get_info_needs(&sized, &get_active_cols(&sized));
+        assert!(needs.stat && !needs.content);
+
+        let json = Args {
+            format: OutputFormat::Json,
+            ..sized
+        };
+        assert_eq!(get_info_needs(&json, &get_active_cols(&json)), InfoNeeds::ALL);
+    }
+
+    fn make_tree_dir() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("sub/deeper")).unwrap();
+        File::create(dir.path().join("sub/deeper/leaf.txt")).unwrap();
+        File::create(dir.path().join("sub/inner.txt")).unwrap();
+        File::create(dir.path().join("top.txt")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_get_files_tree_unlimited() {
+        let dir = make_tree_dir();
+        let args = Args {
+            tree: true,
+            ..Args::default()
+        };
+        let rows = get_files(dir.path().to_str().unwrap(), &args).unwrap();
+        let names: Vec<&str> = rows.iter().map(|r| r.render.srcname.as_str()).collect();
+        assert_eq!(names, ["sub/", "deeper/", "leaf.txt", "inner.txt", "top.txt"]);
+    }
+
+    #[test]
+    fn test_get_files_tree_connectors() {
+        let dir = make_tree_dir();
+        let args = Args {
+            tree: true,
+            ..Args::default()
+        };
+        let rows = get_files(dir.path().to_str().unwrap(), &args).unwrap();
+        assert_eq!(rows[0].render.tree, "├── ");
+        assert_eq!(rows[1].render.tree, "│   ├── ");
+        assert_eq!(rows[2].render.tree, "│   │   └── ");
+        assert_eq!(rows[3].render.tree, "│   └── ");
+        assert_eq!(rows[4].render.tree, "└── ");
+    }
+
+    fn make_repo_dir() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join(".git")).unwrap();
+        fs::create_dir_all(dir.path().join("src/gen")).unwrap();
+        fs::create_dir_all(dir.path().join("target")).unwrap();
+        fs::write(dir.path().join(".gitignore"), "target/\n").unwrap();
+        fs::write(dir.path().join("src/.gitignore"), "gen/\n").unwrap();
+        File::create(dir.path().join("src/main.rs")).unwrap();
+        File::create(dir.path().join("src/gen/out.rs")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_get_files_gitignore_hide_nested() {
+        let dir = make_repo_dir();
+        let args = Args {
+            tree: true,
+            gitignore: Some(IgnoreMode::Hide),
+            ..Args::default()
+        };
+        let rows = get_files(path_in(&dir, "src").as_str(), &args).unwrap();
+        let names: Vec<&str> = rows.iter().map(|r| r.render.srcname.as_str()).collect();
+        assert_eq!(names, [".gitignore", "main.rs"]);
+
+        let rows = get_files(dir.path().to_str().unwrap(), &args).unwrap();
+        assert!(!rows.iter().any(|r| r.render.srcname == "target/"));
+        assert!(!rows.iter().any(|r| r.render.srcname == ".git/"));
+    }
+
+    #[test]
+    fn test_get_files_gitignore_dim() {
+        let dir = make_repo_dir();
+        let args = Args {
+            gitignore: Some(IgnoreMode::Dim),
+            ..Args::default()
+        };
+        let rows = get_files(dir.path().to_str().unwrap(), &args).unwrap();
+        let dimmed: Vec<&str> = rows
+            .iter()
+            .filter(|r| r.render.dimmed)
+            .map(|r| r.render.srcname.as_str())
+            .collect();
+        assert_eq!(dimmed, [".git/", "target/"]);
+    }
+
+    #[test]
+    fn test_get_sections_gitignore_file_operands() {
+        let dir = make_repo_dir();
+        fs::write(dir.path().join("src/.gitignore"), "gen/\n*.bak\n").unwrap();
+        File::create(dir.path().join("src/old.bak")).unwrap();
+        let start_paths = vec![path_in(&dir, "src/old.bak"), path_in(&dir, "src/main.rs")];
+
+        let args = Args {
+            start_paths: start_paths.clone(),
+            gitignore: Some(IgnoreMode::Hide),
+            ..Args::default()
+        };
+        let (sections, _) = get_sections(&args);
+        assert_eq!(sections[0].rows.len(), 1);
+        assert!(sections[0].rows[0].info.fname.ends_with("main.rs"));
+
+        let args = Args {
+            start_paths,
+            gitignore: Some(IgnoreMode::Dim),
+            ..Args::default()
+        };
+        let (sections, _) = get_sections(&args);
+        let dimmed: Vec<bool> = sections[0].rows.iter().map(|r| r.render.dimmed).collect();
+        assert_eq!(dimmed, [false, true]);
+    }
+
+    #[test]
+    fn test_get_files_gitignore_off() {
+        let dir = make_repo_dir();
+        let rows = get_files(dir.path().to_str().unwrap(), &Args::default()).unwrap();
+        assert_eq!(rows.len(), 4);
+        assert!(rows.iter().all(|r| !r.render.dimmed));
+    }
+
+    #[test]
+    fn test_get_files_tree_depth_limit() {
+        let dir = make_tree_dir();
+        let args = Args {
+            tree: true,
+            depth: Some(2),
+            ..Args::default()
+        };
+        let rows = get_files(dir.path().to_str().unwrap(), &args).unwrap();
+        assert_eq!(rows.len(), 4);
+        assert!(!rows.iter().any(|r| r.info.fname.ends_with("leaf.txt")));
+    }
+
+    #[test]
+    fn test_get_files_broken_links_only() {
+        let dir = make_tree_dir();
+        symlink(dir.path().join("missing"), dir.path().join("dead")).unwrap();
+        symlink(dir.path().join("top.txt"), dir.path().join("alive")).unwrap();
+        let args = Args {
+            broken_links: true,
+            ..Args::default()
+        };
+        let rows = get_files(dir.path().to_str().unwrap(), &args).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].info.ftype, FileType::BrokenSymlink);
+    }
+
+    #[test]
+    fn test_get_files_broken_links_tree_keeps_parents() {
+        let dir = make_tree_dir();
+        symlink(dir.path().join("missing"), dir.path().join("sub/deeper/dead")).unwrap();
+        let args = Args {
+            tree: true,
+            broken_links: true,
+            ..Args::default()
+        };
+        let rows = get_files(dir.path().to_str().unwrap(), &args).unwrap();
+        let names: Vec<&str> = rows.iter().map(|r| r.render.srcname.as_str()).collect();
+        assert_eq!(names, ["sub/", "deeper/", "dead@"]);
+        assert_eq!(rows[2].render.tree, "        └── ");
+    }
+
+    #[test]
+    fn test_get_files_tree_glob_keeps_parents() {
+        let dir = make_tree_dir();
+        let args = with_names(Args {
+            tree: true,
+            globs: vec![String::from("leaf*")],
+            ..Args::default()
+        });
+        let rows = get_files(dir.path().to_str().unwrap(), &args).unwrap();
+        let names: Vec<&str> = rows.iter().map(|r| r.render.srcname.as_str()).collect();
+        assert_eq!(names, ["sub/", "deeper/", "leaf.txt"]);
+    }
+
+    #[test]
+    fn test_get_files_tree_glob_matches_dir_names() {
+        let dir = make_tree_dir();
+        let args = with_names(Args {
+            tree: true,
+            globs: vec![String::from("deep*")],
+            ..Args::default()
+        });
+        let rows = get_files(dir.path().to_str().unwrap(), &args).unwrap();
+        let names: Vec<&str> = rows.iter().map(|r| r.render.srcname.as_str()).collect();
+        assert_eq!(names, ["sub/", "deeper/"]);
+    }
+
+    #[test]
+    fn test_get_files_larger_than() {
+        let dir = TempDir::new().unwrap();
+        let mut big = File::create(dir.path().join("big.bin")).unwrap();
+        big.write_all(&[b'x'; 4096]).unwrap();
+        File::create(dir.path().join("small.bin")).unwrap();
+
+        let args = Args {
+            larger_than: Some(1024),
+            ..Args::default()
+        };
+        let rows = get_files(dir.path().to_str().unwrap(), &args).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert!(rows[0].info.fname.ends_with("big.bin"));
+    }
+
+    #[test]
+    fn test_get_files_type_filter_tree() {
+        let dir = make_tree_dir();
+        let args = Args {
+            tree: true,
+            types: vec!['d'],
+            ..Args::default()
+        };
+        let rows = get_files(dir.path().to_str().unwrap(), &args).unwrap();
+        let names: Vec<&str> = rows.iter().map(|r| r.render.srcname.as_str()).collect();
+        assert_eq!(names, ["sub/", "deeper/"]);
+    }
+
+    #[test]
+    fn test_get_files_tree_skips_symlinked_dirs() {
+        let dir = make_tree_dir();
+        symlink(dir.path().join("sub"), dir.path().join("link")).unwrap();
+        let args = Args {
+            tree: true,
+            ..Args::default()
+        };
+        let rows = get_files(dir.path().to_str().unwrap(), &args).unwrap();
+        assert!(!rows.iter().any(|r| r.info.fname.contains("link/")));
+    }
 }
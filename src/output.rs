@@ -0,0 +1,386 @@
+use crate::render::get_col_value;
+use crate::types::{ColType, ContentType, FileRow, FileType, RenderedCols, StatResult};
+
+fn file_type_name(ftype: FileType) -> &'static str {
+    match ftype {
+        FileType::File => "file",
+        FileType::Directory => "directory",
+        FileType::Symlink => "symlink",
+        FileType::BrokenSymlink => "broken_symlink",
+        FileType::Fifo => "fifo",
+        FileType::Socket => "socket",
+        FileType::BlockDevice => "block_device",
+        FileType::CharDevice => "char_device",
+    }
+}
+
+fn content_type_name(content_type: ContentType) -> &'static str {
+    match content_type {
+        ContentType::Directory => "directory",
+        ContentType::NotReadable => "not_readable",
+        ContentType::Empty => "empty",
+        ContentType::BinaryExecutable => "binary_executable",
+        ContentType::BinaryOther => "binary_other",
+        ContentType::Text => "text",
+        ContentType::Other => "other",
+        ContentType::Unknown => "unknown",
+    }
+}
+
+// The terminal renderer uses a lone space for empty cells
+pub fn clean_value(value: &str) -> &str {
+    if value == " " {
+        ""
+    } else {
+        value
+    }
+}
+
+pub fn json_escape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len() + 2);
+    result.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+
+    result.push('"');
+    result
+}
+
+fn stat_to_json(stat_res: &StatResult) -> String {
+    let btime = stat_res
+        .st_btime
+        .map(|t| t.to_string())
+        .unwrap_or_else(|| String::from("null"));
+
+    format!(
+        "{{\"st_mode\":{},\"st_mtime\":{},\"st_uid\":{},\"st_gid\":{},\"st_size\":{},\"st_blocks\":{},\"st_mtime_nsec\":{},\"st_atime\":{},\"st_atime_nsec\":{},\"st_ctime\":{},\"st_ctime_nsec\":{},\"st_btime\":{},\"st_btime_nsec\":{}}}",
+        stat_res.st_mode,
+        stat_res.st_mtime,
+        stat_res.st_uid,
+        stat_res.st_gid,
+        stat_res.st_size,
+        stat_res.st_blocks,
+        stat_res.st_mtime_nsec,
+        stat_res.st_atime,
+        stat_res.st_atime_nsec,
+        stat_res.st_ctime,
+        stat_res.st_ctime_nsec,
+        btime,
+        stat_res.st_btime_nsec
+    )
+}
+
+fn render_to_json(render: &RenderedCols) -> String {
+    let fields = [
+        ("acls", &render.acls),
+        ("owner", &render.owner),
+        ("filetype", &render.filetype),
+        ("size", &render.size),
+        ("timeiso", &render.timeiso),
+        ("gitstatus", &render.gitstatus),
+        ("srcname", &render.srcname),
+        ("targetname", &render.targetname),
+        ("preview", &render.preview),
+        ("tree", &render.tree),
+    ];
+
+    let parts: Vec<String> = fields
+        .iter()
+        .map(|(key, value)| format!("\"{}\":{}", key, json_escape(clean_value(value))))
+        .collect();
+
+    format!("{{{}}}", parts.join(","))
+}
+
+// The link fields are null for anything but a symlink; `link_stat` also for a broken one
+pub fn row_to_json(row: &FileRow) -> String {
+    let info = &row.info;
+    let null = || String::from("null");
+    format!(
+        "{{\"info\":{{\"fname\":{},\"ftype\":\"{}\",\"stat_res\":{},\"content_type\":\"{}\",\"time_epoch\":{},\"link_target\":{},\"link_stat\":{}}},\"render\":{}}}",
+        json_escape(&info.fname),
+        file_type_name(info.ftype),
+        stat_to_json(&info.stat_res),
+        content_type_name(info.content_type),
+        json_escape(&info.time_epoch),
+        info.link_target.as_deref().map_or_else(null, json_escape),
+        info.link_stat.as_ref().map_or_else(null, stat_to_json),
+        render_to_json(&row.render)
+    )
+}
+
+pub fn render_json(rows: &[FileRow]) -> String {
+    if rows.is_empty() {
+        return String::from("[]\n");
+    }
+
+    let rendered: Vec<String> = rows.iter().map(row_to_json).collect();
+    format!("[\n{}\n]\n", rendered.join(",\n"))
+}
+
+pub fn render_ndjson(rows: &[FileRow]) -> String {
+    rows.iter()
+        .map(|row| format!("{}\n", row_to_json(row)))
+        .collect()
+}
+
+pub fn csv_quote(value: &str) -> String {
+    let needs_quotes =
+        value.contains([',', '"', '\n', '\r']) || value.starts_with(' ') || value.ends_with(' ');
+
+    if needs_quotes {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+pub fn tsv_escape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '\\' => result.push_str("\\\\"),
+            '\t' => result.push_str("\\t"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            c => result.push(c),
+        }
+    }
+
+    result
+}
+
+// Spreadsheets get the full path instead of the bare name, so tree rows stay apart, and the
+// raw link target with its broken state in a column of its own
+fn get_delimited_cells(row: &FileRow, col: ColType) -> Vec<String> {
+    let info = &row.info;
+    match col {
+        ColType::SrcName => vec![info.fname.clone()],
+        ColType::TargetName => {
+            let broken = match info.ftype {
+                FileType::BrokenSymlink => "true",
+                FileType::Symlink => "false",
+                _ => "",
+            };
+            vec![
+                info.link_target.clone().unwrap_or_default(),
+                broken.to_string(),
+            ]
+        }
+        _ => vec![clean_value(get_col_value(row, col)).to_string()],
+    }
+}
+
+fn render_delimited(
+    rows: &[FileRow],
+    cols: &[ColType],
+    sep: &str,
+    escape: fn(&str) -> String,
+) -> String {
+    let mut lines: Vec<String> = Vec::with_capacity(rows.len() + 1);
+
+    let header: Vec<String> = cols
+        .iter()
+        .flat_map(|&col| match col {
+            ColType::TargetName => vec![col.name(), "broken"],
+            _ => vec![col.name()],
+        })
+        .map(escape)
+        .collect();
+    lines.push(header.join(sep));
+
+    for row in rows {
+        let record: Vec<String> = cols
+            .iter()
+            .flat_map(|&col| get_delimited_cells(row, col))
+            .map(|value| escape(&value))
+            .collect();
+        lines.push(record.join(sep));
+    }
+
+    format!("{}\n", lines.join("\n"))
+}
+
+pub fn render_csv(rows: &[FileRow], cols: &[ColType]) -> String {
+    render_delimited(rows, cols, ",", csv_quote)
+}
+
+pub fn render_tsv(rows: &[FileRow], cols: &[ColType]) -> String {
+    render_delimited(rows, cols, "\t", tsv_escape)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::fixtures;
+
+    fn make_test_row(fname: &str) -> FileRow {
+        let mut row = fixtures::make_test_row(fname, FileType::File);
+        row.render = RenderedCols {
+            size: String::from("1,024"),
+            srcname: String::from(fname),
+            targetname: String::from(" "),
+            ..RenderedCols::default()
+        };
+        row
+    }
+
+    #[test]
+    fn test_json_escape_plain() {
+        assert_eq!(json_escape("hello"), "\"hello\"");
+    }
+
+    #[test]
+    fn test_json_escape_quotes_and_backslash() {
+        assert_eq!(json_escape("a\"b\\c"), "\"a\\\"b\\\\c\"");
+    }
+
+    #[test]
+    fn test_json_escape_control_chars() {
+        assert_eq!(json_escape("a\nb\tc"), "\"a\\nb\\tc\"");
+        assert_eq!(json_escape("\x01"), "\"\\u0001\"");
+    }
+
+    #[test]
+    fn test_clean_value_placeholder() {
+        assert_eq!(clean_value(" "), "");
+        assert_eq!(clean_value("abc"), "abc");
+    }
+
+    #[test]
+    fn test_row_to_json_fields() {
+        let row = make_test_row("file.txt");
+        let result = row_to_json(&row);
+        assert!(result.starts_with("{\"info\":{\"fname\":\"file.txt\""));
+        assert!(result.contains("\"ftype\":\"file\""));
+        assert!(result.contains("\"st_size\":1024"));
+        assert!(result.contains("\"st_btime\":null"));
+        assert!(result.contains("\"content_type\":\"text\""));
+        assert!(result.contains("\"size\":\"1,024\""));
+        assert!(result.contains("\"targetname\":\"\""));
+        assert!(result.contains("\"link_target\":null,\"link_stat\":null}"));
+    }
+
+    #[test]
+    fn test_row_to_json_symlink() {
+        let mut row = make_test_row("link");
+        row.info.ftype = FileType::Symlink;
+        row.info.link_target = Some(String::from("real \"file\".txt"));
+        row.info.link_stat = Some(StatResult {
+            st_size: 7,
+            ..StatResult::default()
+        });
+        let result = row_to_json(&row);
+        assert!(result.contains("\"link_target\":\"real \\\"file\\\".txt\""));
+        assert!(result.contains("\"link_stat\":{\"st_mode\":0,"));
+        assert!(result.contains("\"st_size\":7,"));
+    }
+
+    #[test]
+    fn test_render_json_empty() {
+        assert_eq!(render_json(&[]), "[]\n");
+    }
+
+    #[test]
+    fn test_render_json_array() {
+        let rows = [make_test_row("a"), make_test_row("b")];
+        let result = render_json(&rows);
+        assert!(result.starts_with("[\n{"));
+        assert!(result.ends_with("}\n]\n"));
+        assert_eq!(result.matches("},\n{").count(), 1);
+    }
+
+    #[test]
+    fn test_render_ndjson_one_line_per_row() {
+        let rows = [make_test_row("a"), make_test_row("b")];
+        let result = render_ndjson(&rows);
+        assert_eq!(result.lines().count(), 2);
+        assert!(result
+            .lines()
+            .all(|l| l.starts_with('{') && l.ends_with('}')));
+    }
+
+    #[test]
+    fn test_render_ndjson_empty() {
+        assert_eq!(render_ndjson(&[]), "");
+    }
+
+    #[test]
+    fn test_csv_quote_plain() {
+        assert_eq!(csv_quote("file.txt"), "file.txt");
+    }
+
+    #[test]
+    fn test_csv_quote_comma_and_quotes() {
+        assert_eq!(csv_quote("a,b"), "\"a,b\"");
+        assert_eq!(csv_quote("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn test_csv_quote_newline_and_edge_spaces() {
+        assert_eq!(csv_quote("a\nb"), "\"a\nb\"");
+        assert_eq!(csv_quote(" a"), "\" a\"");
+    }
+
+    #[test]
+    fn test_tsv_escape() {
+        assert_eq!(tsv_escape("a\tb\nc\\d"), "a\\tb\\nc\\\\d");
+    }
+
+    #[test]
+    fn test_render_csv_header_and_records() {
+        let rows = [make_test_row("a,b.txt")];
+        let cols = [ColType::Size, ColType::SrcName, ColType::TargetName];
+        let result = render_csv(&rows, &cols);
+        assert_eq!(result, "size,name,target,broken\n\"1,024\",\"a,b.txt\",,\n");
+    }
+
+    #[test]
+    fn test_render_csv_full_path_and_raw_target() {
+        let mut row = make_test_row("benches/syscalls.rs");
+        row.render.srcname = String::from("syscalls.rs");
+        let mut link = make_test_row("src/link");
+        link.info.ftype = FileType::BrokenSymlink;
+        link.info.link_target = Some(String::from("/very/long/path/to/some/inside.txt"));
+        link.render.targetname = String::from("/very/lo...inside.txt [broken]");
+
+        let cols = [ColType::SrcName, ColType::TargetName];
+        let result = render_csv(&[row, link], &cols);
+        assert_eq!(
+            result,
+            "name,target,broken\nbenches/syscalls.rs,,\nsrc/link,/very/long/path/to/some/inside.txt,true\n"
+        );
+    }
+
+    #[test]
+    fn test_render_csv_no_ansi() {
+        let rows = [make_test_row("a")];
+        let cols = [ColType::Size, ColType::SrcName];
+        assert!(!render_csv(&rows, &cols).contains('\x1b'));
+    }
+
+    #[test]
+    fn test_render_tsv_header_and_records() {
+        let rows = [make_test_row("a b.txt")];
+        let cols = [ColType::Size, ColType::SrcName];
+        let result = render_tsv(&rows, &cols);
+        assert_eq!(result, "size\tname\n1,024\ta b.txt\n");
+    }
+
+    #[test]
+    fn test_render_csv_empty_has_header() {
+        let cols = [ColType::Size, ColType::SrcName];
+        assert_eq!(render_csv(&[], &cols), "size,name\n");
+    }
+}
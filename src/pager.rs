@@ -0,0 +1,740 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::sync::atomic::{AtomicI32, Ordering};
+
+use crate::terminal::get_terminal_size;
+use crate::utils::{char_width, next_token, strip_ansi, visible_width};
+
+const SGR_RESET: &str = "\x1b[0m";
+const POLL_MS: libc::c_int = 250;
+const HANDLED_SIGNALS: [libc::c_int; 5] =
+    [libc::SIGINT, libc::SIGQUIT, libc::SIGTERM, libc::SIGTSTP, libc::SIGCONT];
+
+// Set by the signal handler and acted on between keys, where touching the terminal is safe
+static PENDING_SIGNAL: AtomicI32 = AtomicI32::new(0);
+
+extern "C" fn note_signal(sig: libc::c_int) {
+    PENDING_SIGNAL.store(sig, Ordering::Relaxed);
+}
+
+// None puts back the default action
+fn set_handler(sig: libc::c_int, handler: Option<extern "C" fn(libc::c_int)>) -> libc::sigaction {
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        let mut previous: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = handler.map_or(libc::SIG_DFL, |f| f as libc::sighandler_t);
+        libc::sigemptyset(&mut action.sa_mask);
+        libc::sigaction(sig, &action, &mut previous);
+        previous
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Enter,
+    Backspace,
+    Escape,
+    CtrlC,
+    Unknown,
+}
+
+fn parse_escape(rest: &[u8]) -> (Key, usize) {
+    if rest.len() < 2 || (rest[0] != b'[' && rest[0] != b'O') {
+        return (Key::Escape, 0);
+    }
+
+    let end = match rest[1..].iter().position(|b| (0x40..=0x7e).contains(b)) {
+        Some(pos) => pos + 1,
+        None => return (Key::Unknown, rest.len()),
+    };
+
+    let key = match &rest[1..=end] {
+        b"A" => Key::Up,
+        b"B" => Key::Down,
+        b"H" | b"1~" | b"7~" => Key::Home,
+        b"F" | b"4~" | b"8~" => Key::End,
+        b"5~" => Key::PageUp,
+        b"6~" => Key::PageDown,
+        _ => Key::Unknown,
+    };
+
+    (key, end + 1)
+}
+
+// One read from the terminal can carry several keys, e.g. when a key is held down
+pub fn parse_keys(buf: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut i = 0;
+
+    while i < buf.len() {
+        let (key, len) = match buf[i] {
+            0x1b => {
+                let (key, len) = parse_escape(&buf[i + 1..]);
+                (key, len + 1)
+            }
+            b'\r' | b'\n' => (Key::Enter, 1),
+            0x7f | 0x08 => (Key::Backspace, 1),
+            0x03 => (Key::CtrlC, 1),
+            _ => {
+                let text = String::from_utf8_lossy(&buf[i..]);
+                match text.chars().next() {
+                    Some(c) if c != char::REPLACEMENT_CHARACTER => (Key::Char(c), c.len_utf8()),
+                    _ => (Key::Unknown, 1),
+                }
+            }
+        };
+        keys.push(key);
+        i += len;
+    }
+
+    keys
+}
+
+// Wrapped pieces close any open color and reopen it on the next piece
+pub fn wrap_line(line: &str, width: usize) -> Vec<String> {
+    if width == 0 || visible_width(line) <= width {
+        return vec![line.to_string()];
+    }
+
+    let chars: Vec<char> = line.chars().collect();
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    let mut active = String::new();
+    let mut col = 0;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let (next, is_escape) = next_token(&chars, i);
+        let token: String = chars[i..next].iter().collect();
+
+        if is_escape {
+            if token.ends_with('m') {
+                if token == SGR_RESET || token == "\x1b[m" {
+                    active.clear();
+                } else {
+                    active.push_str(&token);
+                }
+            }
+            current.push_str(&token);
+        } else {
+            // A wide character that would straddle the edge moves to the next piece
+            let char_cols = char_width(chars[i]);
+            if col > 0 && col + char_cols > width {
+                if !active.is_empty() {
+                    current.push_str(SGR_RESET);
+                }
+                pieces.push(std::mem::take(&mut current));
+                current.push_str(&active);
+                col = 0;
+            }
+            current.push_str(&token);
+            col += char_cols;
+        }
+        i = next;
+    }
+
+    pieces.push(current);
+    pieces
+}
+
+// Smart case, like `less -i`: an uppercase letter in the pattern makes it case-sensitive
+pub fn find_match(lines: &[String], pattern: &str, start: usize, forward: bool) -> Option<usize> {
+    let case_sensitive = pattern.chars().any(char::is_uppercase);
+    let needle = if case_sensitive {
+        pattern.to_string()
+    } else {
+        pattern.to_lowercase()
+    };
+
+    let matches = |idx: &usize| {
+        let line = &lines[*idx];
+        if case_sensitive {
+            line.contains(&needle)
+        } else {
+            line.to_lowercase().contains(&needle)
+        }
+    };
+
+    if forward {
+        (start..lines.len()).find(matches)
+    } else {
+        (0..=start.min(lines.len().saturating_sub(1)))
+            .rev()
+            .find(matches)
+    }
+}
+
+pub struct Pager {
+    lines: Vec<String>,
+    screen: Vec<String>,
+    plain: Vec<String>,
+    top: usize,
+    width: usize,
+    height: usize,
+    pattern: Option<String>,
+    // Screen line of the last match; `n` and `N` move on from it, not from the top
+    matched: Option<usize>,
+    prompt: Option<String>,
+    message: Option<String>,
+}
+
+impl Pager {
+    pub fn new(content: &str, width: usize, height: usize) -> Self {
+        let mut pager = Self {
+            lines: content.lines().map(String::from).collect(),
+            screen: Vec::new(),
+            plain: Vec::new(),
+            top: 0,
+            width,
+            height,
+            pattern: None,
+            matched: None,
+            prompt: None,
+            message: None,
+        };
+        pager.layout();
+        pager
+    }
+
+    fn layout(&mut self) {
+        self.screen = self
+            .lines
+            .iter()
+            .flat_map(|line| wrap_line(line, self.width))
+            .collect();
+        self.plain = self.screen.iter().map(|line| strip_ansi(line)).collect();
+        self.top = self.top.min(self.max_top());
+        self.matched = None;
+    }
+
+    pub fn resize(&mut self, width: usize, height: usize) {
+        if (width, height) != (self.width, self.height) {
+            self.width = width;
+            self.height = height;
+            self.layout();
+        }
+    }
+
+    // The last terminal row is kept for the status line
+    fn page_rows(&self) -> usize {
+        self.height.saturating_sub(1).max(1)
+    }
+
+    fn max_top(&self) -> usize {
+        self.screen.len().saturating_sub(self.page_rows())
+    }
+
+    pub fn fits_one_screen(&self) -> bool {
+        self.screen.len() <= self.page_rows()
+    }
+
+    // Moving forward from the end quits, like `less --quit-at-eof`
+    fn scroll(&mut self, delta: isize) -> bool {
+        if delta > 0 && self.top >= self.max_top() {
+            return false;
+        }
+        self.top = self.top.saturating_add_signed(delta).min(self.max_top());
+        true
+    }
+
+    fn search(&mut self, start: usize, forward: bool) {
+        let pattern = match &self.pattern {
+            Some(p) => p,
+            None => {
+                self.message = Some(String::from("No previous search pattern"));
+                return;
+            }
+        };
+
+        match find_match(&self.plain, pattern, start, forward) {
+            Some(idx) => {
+                self.matched = Some(idx);
+                self.top = idx.min(self.max_top());
+            }
+            None => self.message = Some(String::from("Pattern not found")),
+        }
+    }
+
+    fn search_next(&mut self, forward: bool) {
+        let start = match (self.matched, forward) {
+            (Some(idx), true) => idx + 1,
+            (Some(0), false) => {
+                self.message = Some(String::from("Pattern not found"));
+                return;
+            }
+            (Some(idx), false) => idx - 1,
+            (None, true) => self.top + 1,
+            (None, false) => self.top.saturating_sub(1),
+        };
+        self.search(start, forward);
+    }
+
+    fn handle_prompt(&mut self, key: Key) {
+        let buf = match self.prompt.as_mut() {
+            Some(b) => b,
+            None => return,
+        };
+
+        match key {
+            Key::Char(c) => buf.push(c),
+            Key::Backspace => {
+                let removed = buf.pop();
+                if removed.is_none() {
+                    self.prompt = None;
+                }
+            }
+            Key::Enter => {
+                let entered = self.prompt.take().unwrap_or_default();
+                if !entered.is_empty() {
+                    self.pattern = Some(entered);
+                }
+                self.search(self.top, true);
+            }
+            Key::Escape | Key::CtrlC => self.prompt = None,
+            _ => {}
+        }
+    }
+
+    // Returns false once the user asks to quit
+    pub fn handle_key(&mut self, key: Key) -> bool {
+        if self.prompt.is_some() {
+            self.handle_prompt(key);
+            return true;
+        }
+
+        self.message = None;
+        let page = self.page_rows() as isize;
+
+        match key {
+            Key::Char('q') | Key::Char('Q') | Key::CtrlC => return false,
+            Key::Down | Key::Enter | Key::Char('j') | Key::Char('e') => return self.scroll(1),
+            Key::Up | Key::Char('k') | Key::Char('y') => {
+                self.scroll(-1);
+            }
+            Key::PageDown | Key::Char(' ') | Key::Char('f') => return self.scroll(page),
+            Key::PageUp | Key::Char('b') => {
+                self.scroll(-page);
+            }
+            Key::Char('d') => return self.scroll(page / 2),
+            Key::Char('u') => {
+                self.scroll(-page / 2);
+            }
+            Key::Home | Key::Char('g') => self.top = 0,
+            Key::End | Key::Char('G') => self.top = self.max_top(),
+            Key::Char('/') => self.prompt = Some(String::new()),
+            Key::Char('n') => self.search_next(true),
+            Key::Char('N') => self.search_next(false),
+            _ => {}
+        }
+
+        true
+    }
+
+    fn status(&self) -> String {
+        if let Some(buf) = &self.prompt {
+            return format!("/{}", buf);
+        }
+        if let Some(message) = &self.message {
+            return format!("\x1b[7m{}{}", message, SGR_RESET);
+        }
+        if self.top >= self.max_top() {
+            return format!("\x1b[7m(END){}", SGR_RESET);
+        }
+        String::from(":")
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::from("\x1b[H");
+
+        for row in 0..self.page_rows() {
+            let idx = self.top + row;
+            // The matched line shows in reverse video so it stands out on a page of hits
+            if self.matched == Some(idx) {
+                out.push_str("\x1b[7m");
+                out.push_str(&self.plain[idx]);
+            } else if let Some(line) = self.screen.get(idx) {
+                out.push_str(line);
+            }
+            out.push_str(SGR_RESET);
+            out.push_str("\x1b[K\r\n");
+        }
+
+        out.push_str(&self.status());
+        out.push_str("\x1b[K");
+        out
+    }
+}
+
+// Raw input on the controlling terminal, undone on drop. There is no alternate screen, so
+// the last page stays on screen after quitting, like `less -X`. ISIG stays on, so Ctrl-C,
+// Ctrl-Z and Ctrl-\ still raise their signals
+struct TerminalGuard {
+    tty: File,
+    original: libc::termios,
+    raw: libc::termios,
+    handlers: Vec<(libc::c_int, libc::sigaction)>,
+}
+
+impl TerminalGuard {
+    fn enable(tty: File) -> io::Result<Self> {
+        let fd = tty.as_raw_fd();
+        let mut original: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(fd, &mut original) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut raw = original;
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::IEXTEN);
+        raw.c_iflag &= !(libc::IXON | libc::ICRNL);
+        raw.c_cc[libc::VMIN] = 1;
+        raw.c_cc[libc::VTIME] = 0;
+
+        let handlers = HANDLED_SIGNALS
+            .iter()
+            .map(|&sig| (sig, set_handler(sig, Some(note_signal))))
+            .collect();
+        let guard = Self {
+            tty,
+            original,
+            raw,
+            handlers,
+        };
+        guard.set_mode(&guard.raw)?;
+        Ok(guard)
+    }
+
+    fn set_mode(&self, mode: &libc::termios) -> io::Result<()> {
+        if unsafe { libc::tcsetattr(self.tty.as_raw_fd(), libc::TCSAFLUSH, mode) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    // Hands the terminal back and stops the way the default action would, then takes the
+    // terminal again once the shell continues us
+    fn suspend(&mut self) -> io::Result<()> {
+        self.write("\r\x1b[K")?;
+        self.set_mode(&self.original)?;
+        set_handler(libc::SIGTSTP, None);
+        unsafe { libc::raise(libc::SIGTSTP) };
+        set_handler(libc::SIGTSTP, Some(note_signal));
+        self.set_mode(&self.raw)
+    }
+
+    fn write(&mut self, s: &str) -> io::Result<()> {
+        self.tty.write_all(s.as_bytes())?;
+        self.tty.flush()
+    }
+
+    // Waits briefly for input so resizes can be picked up between keys
+    fn read_keys(&mut self) -> io::Result<Vec<Key>> {
+        let mut pfd = libc::pollfd {
+            fd: self.tty.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+
+        if unsafe { libc::poll(&mut pfd, 1, POLL_MS) } <= 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut buf = [0u8; 64];
+        let len = self.tty.read(&mut buf)?;
+        if len == 0 {
+            return Ok(vec![Key::CtrlC]);
+        }
+        Ok(parse_keys(&buf[..len]))
+    }
+}
+
+impl Drop for TerminalGuard {
+    // Clears the status line so the shell prompt takes its place
+    fn drop(&mut self) {
+        let _ = self.write("\r\x1b[K");
+        let _ = self.set_mode(&self.original);
+        for (sig, previous) in &self.handlers {
+            unsafe { libc::sigaction(*sig, previous, std::ptr::null_mut()) };
+        }
+    }
+}
+
+fn get_size(fd: RawFd) -> (usize, usize) {
+    get_terminal_size(fd).unwrap_or((80, 24))
+}
+
+pub enum PageError {
+    // Nothing was drawn yet, so the caller can still print the content plainly
+    NoTerminal,
+    Io(io::Error),
+}
+
+pub fn page(content: &str) -> Result<(), PageError> {
+    let tty = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .map_err(|_| PageError::NoTerminal)?;
+    let (width, height) = get_size(tty.as_raw_fd());
+    let pager = Pager::new(content, width, height);
+
+    // Short output is printed as-is, like `less --quit-if-one-screen`
+    if pager.fits_one_screen() {
+        let mut stdout = io::stdout();
+        return stdout
+            .write_all(content.as_bytes())
+            .and_then(|_| stdout.flush())
+            .map_err(PageError::Io);
+    }
+
+    let guard = TerminalGuard::enable(tty).map_err(|_| PageError::NoTerminal)?;
+    run(guard, pager, height).map_err(PageError::Io)
+}
+
+fn run(mut guard: TerminalGuard, mut pager: Pager, height: usize) -> io::Result<()> {
+    let mut stdout = io::stdout();
+
+    // Scroll what is already on screen into the scrollback before drawing over it
+    stdout.write_all("\r\n".repeat(height).as_bytes())?;
+
+    loop {
+        stdout.write_all(pager.render().as_bytes())?;
+        stdout.flush()?;
+
+        let keys = guard.read_keys()?;
+        match PENDING_SIGNAL.swap(0, Ordering::Relaxed) {
+            0 => {}
+            libc::SIGINT => break,
+            libc::SIGTSTP => guard.suspend()?,
+            // Stopped some other way; the shell may have reset the terminal meanwhile
+            libc::SIGCONT => guard.set_mode(&guard.raw)?,
+            sig => {
+                // Dies of the signal as asked, once the terminal is back to normal
+                drop(guard);
+                set_handler(sig, None);
+                unsafe { libc::raise(sig) };
+                return Ok(());
+            }
+        }
+        if !keys.into_iter().all(|key| pager.handle_key(key)) {
+            break;
+        }
+
+        let (width, height) = get_size(guard.tty.as_raw_fd());
+        pager.resize(width, height);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::os::unix::io::FromRawFd;
+
+    fn make_pager(count: usize, height: usize) -> Pager {
+        let content: Vec<String> = (0..count).map(|i| format!("line {}", i)).collect();
+        Pager::new(&content.join("\n"), 80, height)
+    }
+
+    #[test]
+    fn test_parse_keys_plain_and_control() {
+        assert_eq!(
+            parse_keys(b"q/\r\x7f\x03"),
+            [
+                Key::Char('q'),
+                Key::Char('/'),
+                Key::Enter,
+                Key::Backspace,
+                Key::CtrlC
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_keys_escape_sequences() {
+        assert_eq!(
+            parse_keys(b"\x1b[A\x1b[B\x1b[5~\x1b[6~\x1bOH\x1b[F"),
+            [
+                Key::Up,
+                Key::Down,
+                Key::PageUp,
+                Key::PageDown,
+                Key::Home,
+                Key::End
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_keys_lone_escape_and_utf8() {
+        assert_eq!(parse_keys(b"\x1b"), [Key::Escape]);
+        assert_eq!(parse_keys("é".as_bytes()), [Key::Char('é')]);
+    }
+
+    #[test]
+    fn test_wrap_line_short() {
+        assert_eq!(wrap_line("abc", 5), ["abc"]);
+        assert_eq!(wrap_line("", 5), [""]);
+    }
+
+    #[test]
+    fn test_wrap_line_plain() {
+        assert_eq!(wrap_line("abcdefg", 3), ["abc", "def", "g"]);
+    }
+
+    #[test]
+    fn test_wrap_line_wide_chars() {
+        assert_eq!(wrap_line("日本語", 4), ["日本", "語"]);
+        assert_eq!(wrap_line("a日本", 2), ["a", "日", "本"]);
+    }
+
+    #[test]
+    fn test_wrap_line_carries_color() {
+        let pieces = wrap_line("\x1b[32mabcd\x1b[0me", 2);
+        assert_eq!(pieces, ["\x1b[32mab\x1b[0m", "\x1b[32mcd\x1b[0m", "e"]);
+    }
+
+    #[test]
+    fn test_find_match_smart_case() {
+        let lines = vec![
+            String::from("Alpha"),
+            String::from("beta"),
+            String::from("ALPHA"),
+        ];
+        assert_eq!(find_match(&lines, "alpha", 1, true), Some(2));
+        assert_eq!(find_match(&lines, "ALPHA", 0, true), Some(2));
+        assert_eq!(find_match(&lines, "alpha", 1, false), Some(0));
+        assert_eq!(find_match(&lines, "gamma", 0, true), None);
+    }
+
+    #[test]
+    fn test_pager_fits_one_screen() {
+        assert!(make_pager(5, 10).fits_one_screen());
+        assert!(!make_pager(10, 10).fits_one_screen());
+    }
+
+    #[test]
+    fn test_pager_scroll_clamps() {
+        let mut pager = make_pager(30, 11);
+        assert!(pager.handle_key(Key::Char(' ')));
+        assert_eq!(pager.top, 10);
+        assert!(pager.handle_key(Key::PageDown));
+        assert_eq!(pager.top, 20);
+        pager.handle_key(Key::Char('g'));
+        assert_eq!(pager.top, 0);
+        pager.handle_key(Key::Up);
+        assert_eq!(pager.top, 0);
+    }
+
+    #[test]
+    fn test_pager_quits_past_end() {
+        let mut pager = make_pager(30, 11);
+        pager.handle_key(Key::End);
+        assert_eq!(pager.top, 20);
+        assert!(!pager.handle_key(Key::Down));
+    }
+
+    #[test]
+    fn test_pager_quit_keys() {
+        let mut pager = make_pager(30, 11);
+        assert!(!pager.handle_key(Key::Char('q')));
+        assert!(!pager.handle_key(Key::CtrlC));
+    }
+
+    #[test]
+    fn test_pager_search() {
+        let mut pager = make_pager(30, 11);
+        for key in parse_keys(b"/7\r") {
+            assert!(pager.handle_key(key));
+        }
+        assert_eq!(pager.top, 7);
+        pager.handle_key(Key::Char('n'));
+        assert_eq!(pager.top, 17);
+        pager.handle_key(Key::Char('N'));
+        assert_eq!(pager.top, 7);
+    }
+
+    #[test]
+    fn test_pager_search_within_last_page() {
+        let mut pager = make_pager(30, 11);
+        for key in parse_keys(b"/2\r") {
+            pager.handle_key(key);
+        }
+        assert_eq!(pager.matched, Some(2));
+        pager.handle_key(Key::Char('n'));
+        pager.handle_key(Key::Char('n'));
+        assert_eq!((pager.top, pager.matched), (20, Some(20)));
+
+        // Matches inside the final screen still advance one at a time
+        pager.handle_key(Key::Char('n'));
+        pager.handle_key(Key::Char('n'));
+        assert_eq!((pager.top, pager.matched), (20, Some(22)));
+        assert!(pager.render().contains("\x1b[7mline 22\x1b[0m"));
+        pager.handle_key(Key::Char('N'));
+        assert_eq!(pager.matched, Some(21));
+    }
+
+    #[test]
+    fn test_pager_search_not_found() {
+        let mut pager = make_pager(30, 11);
+        for key in parse_keys(b"/missing\r") {
+            pager.handle_key(key);
+        }
+        assert_eq!(pager.top, 0);
+        assert!(pager.render().contains("Pattern not found"));
+    }
+
+    #[test]
+    fn test_pager_prompt_cancel() {
+        let mut pager = make_pager(30, 11);
+        for key in parse_keys(b"/ab\x7f\x7f\x7f") {
+            pager.handle_key(key);
+        }
+        assert!(pager.prompt.is_none());
+        assert!(pager.handle_key(Key::Char('j')));
+        assert_eq!(pager.top, 1);
+    }
+
+    #[test]
+    fn test_pager_render_status() {
+        let mut pager = make_pager(30, 11);
+        assert!(pager.render().ends_with(":\x1b[K"));
+        pager.handle_key(Key::End);
+        assert!(pager.render().contains("(END)"));
+    }
+
+    #[test]
+    fn test_pager_resize_rewraps() {
+        let mut pager = Pager::new("abcdefghij", 80, 3);
+        assert!(pager.fits_one_screen());
+        pager.resize(4, 3);
+        assert_eq!(pager.screen.len(), 3);
+        assert!(!pager.fits_one_screen());
+    }
+
+    #[test]
+    fn test_terminal_guard_keeps_signals() {
+        let (mut master, mut slave) = (0, 0);
+        let (name, mode, size) = (std::ptr::null_mut(), std::ptr::null(), std::ptr::null());
+        let opened = unsafe { libc::openpty(&mut master, &mut slave, name, mode, size) };
+        assert_eq!(opened, 0);
+        let _master = unsafe { File::from_raw_fd(master) };
+        let tty = unsafe { File::from_raw_fd(slave) };
+
+        let guard = TerminalGuard::enable(tty).unwrap();
+        let mut mode: libc::termios = unsafe { std::mem::zeroed() };
+        assert_eq!(unsafe { libc::tcgetattr(slave, &mut mode) }, 0);
+        assert_ne!(mode.c_lflag & libc::ISIG, 0);
+        assert_eq!(mode.c_lflag & (libc::ICANON | libc::ECHO), 0);
+        drop(guard);
+    }
+}
@@ -0,0 +1,95 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+// Stat and content sniffing mostly wait on the disk, so running more threads than cores
+// keeps requests in flight on high-latency filesystems like NFS
+const THREADS_PER_CORE: usize = 4;
+const MAX_WORKERS: usize = 16;
+const MIN_ITEMS_PER_WORKER: usize = 32;
+
+pub fn get_worker_count(items: usize) -> usize {
+    let cores = thread::available_parallelism().map_or(1, |n| n.get());
+    let wanted = (items / MIN_ITEMS_PER_WORKER).max(1);
+    (cores * THREADS_PER_CORE).min(MAX_WORKERS).min(wanted)
+}
+
+// Like `items.iter().map(f).collect()`, spread over a scoped worker pool.
+// Workers pull the next index from a shared counter, and results come back in input order.
+pub fn parallel_map<T, R, F>(items: &[T], f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let workers = get_worker_count(items.len());
+    if workers <= 1 {
+        return items.iter().map(f).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<(usize, R)> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        match items.get(i) {
+                            Some(item) => done.push((i, f(item))),
+                            None => return done,
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+            .collect()
+    });
+
+    results.sort_unstable_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, r)| r).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_worker_count_small_inputs_stay_serial() {
+        assert_eq!(get_worker_count(0), 1);
+        assert_eq!(get_worker_count(MIN_ITEMS_PER_WORKER - 1), 1);
+    }
+
+    #[test]
+    fn test_worker_count_capped() {
+        let count = get_worker_count(1_000_000);
+        assert!((1..=MAX_WORKERS).contains(&count));
+    }
+
+    #[test]
+    fn test_parallel_map_preserves_order() {
+        let items: Vec<usize> = (0..5000).collect();
+        let result = parallel_map(&items, |n| n * 2);
+        let expected: Vec<usize> = items.iter().map(|n| n * 2).collect();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_parallel_map_empty() {
+        let items: Vec<u8> = Vec::new();
+        assert!(parallel_map(&items, |&b| b).is_empty());
+    }
+
+    #[test]
+    fn test_parallel_map_visits_each_item_once() {
+        let items: Vec<usize> = (0..2000).collect();
+        let seen = Mutex::new(HashSet::new());
+        parallel_map(&items, |&n| assert!(seen.lock().unwrap().insert(n)));
+        assert_eq!(seen.lock().unwrap().len(), items.len());
+    }
+}
@@ -1,8 +1,11 @@
 use std::collections::HashMap;
-use std::fs::{self, File, OpenOptions};
+use std::ffi::CString;
+use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::os::unix::ffi::OsStrExt;
 use std::path::Path;
 
+// Filled once up front and only read afterwards
 pub struct UserGroupCache {
     users: HashMap<u32, String>,
     groups: HashMap<u32, String>,
@@ -44,7 +47,7 @@ fn parse_passwd() -> HashMap<u32, String> {
     };
 
     let reader = BufReader::new(file);
-    for line in reader.lines().flatten() {
+    for line in reader.lines().map_while(Result::ok) {
         let parts: Vec<&str> = line.split(':').collect();
         if parts.len() >= 3 {
             if let Ok(uid) = parts[2].parse::<u32>() {
@@ -63,7 +66,7 @@ fn parse_group() -> HashMap<u32, String> {
     };
 
     let reader = BufReader::new(file);
-    for line in reader.lines().flatten() {
+    for line in reader.lines().map_while(Result::ok) {
         let parts: Vec<&str> = line.split(':').collect();
         if parts.len() >= 3 {
             if let Ok(gid) = parts[2].parse::<u32>() {
@@ -81,63 +84,56 @@ pub fn get_acls_all(mode: u32) -> String {
     format!("{}{}{}", user, group, other)
 }
 
-pub fn get_acls_me(path: &Path) -> String {
-    let mut val = 0u8;
-
-    if File::open(path).is_ok() {
-        val |= 4;
-    }
-
-    if OpenOptions::new().write(true).open(path).is_ok() {
-        val |= 2;
-    }
-
-    if is_executable(path) {
-        val |= 1;
-    }
-
-    val.to_string()
-}
-
-fn is_executable(path: &Path) -> bool {
-    let metadata = match fs::metadata(path) {
-        Ok(m) => m,
+// Uses access(2) rather than opening the file, which would block on fifos. The kernel
+// answers for the real uid and gid, taking ACLs, read-only mounts and capabilities into account
+fn can_access(path: &Path, mode: libc::c_int) -> bool {
+    let c_path = match CString::new(path.as_os_str().as_bytes()) {
+        Ok(p) => p,
         Err(_) => return false,
     };
+    unsafe { libc::access(c_path.as_ptr(), mode) == 0 }
+}
 
-    use std::os::unix::fs::PermissionsExt;
-    let mode = metadata.permissions().mode();
-
-    let uid = unsafe { libc::getuid() };
-    let gid = unsafe { libc::getgid() };
-    let file_uid = metadata.uid();
-    let file_gid = metadata.gid();
-
-    use std::os::unix::fs::MetadataExt;
-
-    if uid == file_uid {
-        return (mode & 0o100) != 0;
-    }
-
-    if gid == file_gid {
-        return (mode & 0o010) != 0;
-    }
+// access(2) follows symlinks, so `mode` is the target's, and a broken link has none and
+// reports nothing. All the bits worth asking about go in one call, and only a refusal is
+// split up per bit; execute is left out when no one has an execute bit
+pub fn get_acls_me(path: &Path, mode: Option<u32>) -> String {
+    let mode = match mode {
+        Some(m) => m,
+        None => return String::from("0"),
+    };
+    let bits = [(libc::R_OK, 4u8), (libc::W_OK, 2), (libc::X_OK, 1)];
+    let wanted: Vec<(libc::c_int, u8)> = bits
+        .into_iter()
+        .filter(|&(check, _)| check != libc::X_OK || mode & 0o111 != 0)
+        .collect();
+
+    let all = wanted.iter().fold(0, |acc, &(check, _)| acc | check);
+    let val: u8 = if can_access(path, all) {
+        wanted.iter().map(|&(_, digit)| digit).sum()
+    } else {
+        wanted
+            .iter()
+            .filter(|&&(check, _)| can_access(path, check))
+            .map(|&(_, digit)| digit)
+            .sum()
+    };
 
-    (mode & 0o001) != 0
+    val.to_string()
 }
 
-pub fn col_acls(path: &Path, mode: u32) -> String {
+pub fn col_acls(path: &Path, mode: u32, followed_mode: Option<u32>) -> String {
     let all_acls = get_acls_all(mode);
-    let me_acls = get_acls_me(path);
+    let me_acls = get_acls_me(path, followed_mode);
     format!("{} {}", all_acls, me_acls)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use tempfile::TempDir;
-    use std::fs::File;
+    use std::fs;
     use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
 
     #[test]
     fn test_get_acls_all_755() {
@@ -175,6 +171,18 @@ mod tests {
         let _cache = UserGroupCache::default();
     }
 
+    #[test]
+    fn test_user_group_cache_shared_across_threads() {
+        let cache = UserGroupCache::new();
+        let names: Vec<String> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|_| scope.spawn(|| cache.get_user_name(99999)))
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        assert!(names.iter().all(|n| n == "99999"));
+    }
+
     #[test]
     fn test_get_user_name_unknown() {
         let cache = UserGroupCache::new();
@@ -196,7 +204,7 @@ mod tests {
         File::create(&file_path).unwrap();
         fs::set_permissions(&file_path, fs::Permissions::from_mode(0o644)).unwrap();
 
-        let result = get_acls_me(&file_path);
+        let result = get_acls_me(&file_path, Some(0o644));
         assert!(result.contains('4') || result.contains('6') || result.contains('7'));
     }
 
@@ -207,10 +215,47 @@ mod tests {
         File::create(&file_path).unwrap();
         fs::set_permissions(&file_path, fs::Permissions::from_mode(0o666)).unwrap();
 
-        let result = get_acls_me(&file_path);
+        let result = get_acls_me(&file_path, Some(0o666));
         assert!(result.contains('6') || result.contains('7'));
     }
 
+    #[test]
+    fn test_get_acls_me_fifo_does_not_block() {
+        let dir = TempDir::new().unwrap();
+        let fifo_path = dir.path().join("pipe");
+        let c_path = CString::new(fifo_path.to_str().unwrap()).unwrap();
+        assert_eq!(unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) }, 0);
+
+        let result = get_acls_me(&fifo_path, Some(0o600));
+        assert_eq!(result, "6");
+    }
+
+    #[test]
+    fn test_get_acls_me_broken_link() {
+        let dir = TempDir::new().unwrap();
+        let link_path = dir.path().join("dangling");
+        std::os::unix::fs::symlink(dir.path().join("missing"), &link_path).unwrap();
+        assert_eq!(get_acls_me(&link_path, None), "0");
+    }
+
+    #[test]
+    fn test_get_acls_me_executable() {
+        let dir = TempDir::new().unwrap();
+        let file_path = dir.path().join("run.sh");
+        File::create(&file_path).unwrap();
+        fs::set_permissions(&file_path, fs::Permissions::from_mode(0o700)).unwrap();
+        assert_eq!(get_acls_me(&file_path, Some(0o700)), "7");
+    }
+
+    #[test]
+    fn test_get_acls_me_skips_execute_without_bits() {
+        let dir = TempDir::new().unwrap();
+        let file_path = dir.path().join("plain.txt");
+        File::create(&file_path).unwrap();
+        fs::set_permissions(&file_path, fs::Permissions::from_mode(0o600)).unwrap();
+        assert_eq!(get_acls_me(&file_path, Some(0o600)), "6");
+    }
+
     #[test]
     fn test_col_acls_format() {
         let dir = TempDir::new().unwrap();
@@ -218,7 +263,7 @@ mod tests {
         File::create(&file_path).unwrap();
         fs::set_permissions(&file_path, fs::Permissions::from_mode(0o644)).unwrap();
 
-        let result = col_acls(&file_path, 0o644);
+        let result = col_acls(&file_path, 0o644, Some(0o644));
         assert!(result.starts_with("644 "));
     }
 }
@@ -21,14 +21,14 @@ pub fn preview_directory(fname: &str) -> String {
         .filter_map(|e| e.ok())
         .map(|e| {
             let name = e.file_name().to_string_lossy().to_string();
-            let entry_path = e.path();
-            let real_path = if entry_path.is_symlink() {
-                fs::canonicalize(&entry_path).unwrap_or(entry_path.clone())
-            } else {
-                entry_path.clone()
+            // The entry type comes from readdir, so only symlinks need a stat to follow them
+            let is_dir = match e.file_type() {
+                Ok(t) if t.is_symlink() => fs::metadata(e.path()).is_ok_and(|m| m.is_dir()),
+                Ok(t) => t.is_dir(),
+                Err(_) => false,
             };
 
-            if real_path.is_dir() {
+            if is_dir {
                 format!("{}/", name)
             } else {
                 name
@@ -144,6 +144,18 @@ mod tests {
         assert!(result.contains('/'));
     }
 
+    #[test]
+    fn test_preview_directory_follows_symlinks() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("real")).unwrap();
+        std::os::unix::fs::symlink("real", dir.path().join("ln")).unwrap();
+        std::os::unix::fs::symlink("gone", dir.path().join("x")).unwrap();
+
+        let result = preview_directory(dir.path().to_str().unwrap());
+        assert!(result.contains("ln/"));
+        assert!(result.split(' ').any(|name| name == "x"));
+    }
+
     #[test]
     fn test_preview_directory_nonexistent() {
         let result = preview_directory("/nonexistent/path/12345");
@@ -1,29 +1,39 @@
 use crate::colors::{add_color, get_color_for_field};
-use crate::types::{Align, ColPaddings, ColType, FileRow};
+use crate::columns::get_col_defs;
+use crate::types::{Align, ColPaddings, ColType, FileRow, RenderOptions};
+use crate::utils::{take_width, truncate_middle, visible_width};
+
+const COL_MARGIN: usize = 2;
+const MIN_PREVIEW_WIDTH: usize = 1;
+const MIN_NAME_WIDTH: usize = 12;
 
 pub fn get_col_paddings(rows: &[FileRow]) -> ColPaddings {
     let mut paddings = ColPaddings::default();
 
     for row in rows {
-        paddings.acls = paddings.acls.max(row.render.acls.chars().count());
-        paddings.owner = paddings.owner.max(row.render.owner.chars().count());
-        paddings.filetype = paddings.filetype.max(row.render.filetype.chars().count());
-        paddings.size = paddings.size.max(row.render.size.chars().count());
-        paddings.timeiso = paddings.timeiso.max(row.render.timeiso.chars().count());
-        paddings.srcname = paddings.srcname.max(row.render.srcname.chars().count());
-        paddings.targetname = paddings.targetname.max(row.render.targetname.chars().count());
-        paddings.preview = paddings.preview.max(row.render.preview.chars().count());
+        paddings.acls = paddings.acls.max(visible_width(&row.render.acls));
+        paddings.owner = paddings.owner.max(visible_width(&row.render.owner));
+        paddings.filetype = paddings.filetype.max(visible_width(&row.render.filetype));
+        paddings.size = paddings.size.max(visible_width(&row.render.size));
+        paddings.timeiso = paddings.timeiso.max(visible_width(&row.render.timeiso));
+        paddings.gitstatus = paddings.gitstatus.max(visible_width(&row.render.gitstatus));
+        let tree_len = visible_width(&row.render.tree);
+        paddings.srcname = paddings.srcname.max(tree_len + visible_width(&row.render.srcname));
+        paddings.tree = paddings.tree.max(tree_len);
+        paddings.targetname = paddings.targetname.max(visible_width(&row.render.targetname));
+        paddings.preview = paddings.preview.max(visible_width(&row.render.preview));
     }
 
     paddings
 }
 
+// Widths are terminal cells, so wide characters pad like they print
 pub fn add_padding(text: &str, width: usize, align: Align) -> String {
     if text.is_empty() {
         return String::from(" ");
     }
 
-    let text_len = text.chars().count();
+    let text_len = visible_width(text);
     if text_len >= width {
         return text.to_string();
     }
@@ -38,33 +48,31 @@ pub fn add_padding(text: &str, width: usize, align: Align) -> String {
 }
 
 pub fn get_cols_listing(full: bool) -> Vec<ColType> {
-    let mut cols = Vec::new();
-
-    if full {
-        cols.push(ColType::Acls);
-        cols.push(ColType::Owner);
-        cols.push(ColType::FileType);
-    }
-
-    cols.push(ColType::Size);
-    cols.push(ColType::TimeIso);
-    cols.push(ColType::SrcName);
-    cols.push(ColType::TargetName);
+    get_col_defs()
+        .into_iter()
+        .filter(|def| full || !def.only_full)
+        .map(|def| def.name)
+        .collect()
+}
 
-    if full {
-        cols.push(ColType::Preview);
+pub fn parse_cols_listing(spec: &str) -> Result<Vec<ColType>, String> {
+    if spec.trim().is_empty() {
+        return Err(String::from("no columns given"));
     }
 
-    cols
+    spec.split(',')
+        .map(|name| name.trim().parse::<ColType>())
+        .collect()
 }
 
-fn get_col_value(row: &FileRow, col: ColType) -> &str {
+pub fn get_col_value(row: &FileRow, col: ColType) -> &str {
     match col {
         ColType::Acls => &row.render.acls,
         ColType::Owner => &row.render.owner,
         ColType::FileType => &row.render.filetype,
         ColType::Size => &row.render.size,
         ColType::TimeIso => &row.render.timeiso,
+        ColType::GitStatus => &row.render.gitstatus,
         ColType::SrcName => &row.render.srcname,
         ColType::TargetName => &row.render.targetname,
         ColType::Preview => &row.render.preview,
@@ -78,12 +86,69 @@ fn get_col_padding(paddings: &ColPaddings, col: ColType) -> usize {
         ColType::FileType => paddings.filetype,
         ColType::Size => paddings.size,
         ColType::TimeIso => paddings.timeiso,
+        ColType::GitStatus => paddings.gitstatus,
         ColType::SrcName => paddings.srcname,
         ColType::TargetName => paddings.targetname,
         ColType::Preview => paddings.preview,
     }
 }
 
+fn get_col_padding_mut(paddings: &mut ColPaddings, col: ColType) -> &mut usize {
+    match col {
+        ColType::Acls => &mut paddings.acls,
+        ColType::Owner => &mut paddings.owner,
+        ColType::FileType => &mut paddings.filetype,
+        ColType::Size => &mut paddings.size,
+        ColType::TimeIso => &mut paddings.timeiso,
+        ColType::GitStatus => &mut paddings.gitstatus,
+        ColType::SrcName => &mut paddings.srcname,
+        ColType::TargetName => &mut paddings.targetname,
+        ColType::Preview => &mut paddings.preview,
+    }
+}
+
+// Empty cells still render a single space
+pub fn get_row_width(paddings: &ColPaddings, cols: &[ColType]) -> usize {
+    cols.iter()
+        .map(|&col| COL_MARGIN + get_col_padding(paddings, col).max(1))
+        .sum()
+}
+
+// Takes space from Preview first, then TargetName, then SrcName, until rows fit `width`.
+// Names keep their minimum width past the deepest tree prefix
+pub fn fit_paddings(paddings: &mut ColPaddings, cols: &[ColType], width: usize) {
+    let shrinkable = [
+        (ColType::Preview, MIN_PREVIEW_WIDTH),
+        (ColType::TargetName, MIN_NAME_WIDTH),
+        (ColType::SrcName, paddings.tree + MIN_NAME_WIDTH),
+    ];
+
+    for (col, min) in shrinkable {
+        let overflow = get_row_width(paddings, cols).saturating_sub(width);
+        if overflow == 0 {
+            return;
+        }
+        if !cols.contains(&col) {
+            continue;
+        }
+
+        let padding = get_col_padding_mut(paddings, col);
+        *padding = padding.saturating_sub(overflow).max(min.min(*padding));
+    }
+}
+
+fn fit_value(value: &str, col: ColType, width: usize) -> String {
+    if visible_width(value) <= width {
+        return value.to_string();
+    }
+
+    match col {
+        ColType::Preview => take_width(value, width),
+        ColType::SrcName | ColType::TargetName => truncate_middle(value, width.max(3)),
+        _ => value.to_string(),
+    }
+}
+
 fn get_col_align(col: ColType) -> Align {
     match col {
         ColType::Size => Align::Right,
@@ -91,65 +156,149 @@ fn get_col_align(col: ColType) -> Align {
     }
 }
 
-fn make_pretty(row: &FileRow, col: ColType, paddings: &ColPaddings) -> String {
+fn make_pretty(
+    row: &FileRow,
+    col: ColType,
+    paddings: &ColPaddings,
+    opts: &RenderOptions,
+) -> String {
     let value = get_col_value(row, col);
-    let width = get_col_padding(paddings, col);
+    let mut width = get_col_padding(paddings, col);
     let align = get_col_align(col);
-    let color = get_color_for_field(row, col);
+    let color = get_color_for_field(row, col, opts);
+
+    // Tree connectors stay uncolored and share the name column's width
+    let prefix = if col == ColType::SrcName { row.render.tree.as_str() } else { "" };
+    width = width.saturating_sub(visible_width(prefix));
 
-    let padded = add_padding(value, width, align);
-    add_color(&padded, color)
+    let padded = add_padding(&fit_value(value, col, width), width, align);
+    if opts.color {
+        format!("{}{}", prefix, add_color(&padded, color))
+    } else {
+        format!("{}{}", prefix, padded)
+    }
 }
 
-pub fn render_cols(row: &FileRow, paddings: &ColPaddings, full: bool) -> String {
+pub fn render_cols(
+    row: &FileRow,
+    paddings: &ColPaddings,
+    cols: &[ColType],
+    opts: &RenderOptions,
+) -> String {
     let margin = "  ";
-    let cols = get_cols_listing(full);
 
-    let rendered: Vec<String> = cols.iter().map(|&col| make_pretty(row, col, paddings)).collect();
+    let rendered: Vec<String> = cols
+        .iter()
+        .map(|&col| make_pretty(row, col, paddings, opts))
+        .collect();
+
+    let line = format!("{}{}", margin, rendered.join(margin));
 
-    format!("{}{}", margin, rendered.join(margin))
+    // Without escapes wrapping it, the last column's padding is just trailing noise
+    if opts.color {
+        line
+    } else {
+        line.trim_end().to_string()
+    }
 }
 
-pub fn render_rows(rows: &[FileRow], full: bool) -> String {
-    let paddings = get_col_paddings(rows);
+pub fn render_rows(rows: &[FileRow], cols: &[ColType], opts: &RenderOptions) -> String {
+    let mut paddings = get_col_paddings(rows);
+    if let Some(width) = opts.width {
+        fit_paddings(&mut paddings, cols, width);
+    }
 
-    let rendered: Vec<String> = rows.iter().map(|row| render_cols(row, &paddings, full)).collect();
+    let rendered: Vec<String> = rows
+        .iter()
+        .map(|row| render_cols(row, &paddings, cols, opts))
+        .collect();
 
     rendered.join("\n")
 }
 
+// Fewest rows whose column-major layout fits `width`; returns the row count and column widths.
+// Like ls, starts from the most columns the narrowest entry allows and works down
+pub fn get_grid_layout(widths: &[usize], width: usize) -> (usize, Vec<usize>) {
+    let count = widths.len();
+    let min_width = match widths.iter().copied().min() {
+        Some(w) => w,
+        None => return (0, vec![0]),
+    };
+    let max_cols = (width / (COL_MARGIN + min_width)).clamp(1, count);
+
+    for cols in (1..=max_cols).rev() {
+        let rows = count.div_ceil(cols);
+        let col_widths: Vec<usize> = widths
+            .chunks(rows)
+            .map(|chunk| chunk.iter().copied().max().unwrap_or(0))
+            .collect();
+        let total: usize = col_widths.iter().map(|w| COL_MARGIN + w).sum();
+
+        if total <= width {
+            return (rows, col_widths);
+        }
+    }
+
+    (count, vec![widths.iter().copied().max().unwrap_or(0)])
+}
+
+pub fn render_grid(rows: &[FileRow], opts: &RenderOptions, width: usize) -> String {
+    let widths: Vec<usize> = rows.iter().map(|r| visible_width(&r.render.srcname)).collect();
+    let (row_count, col_widths) = get_grid_layout(&widths, width);
+    let margin = " ".repeat(COL_MARGIN);
+
+    let lines: Vec<String> = (0..row_count)
+        .map(|i| {
+            let mut line = String::new();
+
+            for (j, &col_width) in col_widths.iter().enumerate() {
+                let row = match rows.get(j * row_count + i) {
+                    Some(r) => r,
+                    None => break,
+                };
+                let padded = add_padding(&row.render.srcname, col_width, Align::Left);
+                let color = get_color_for_field(row, ColType::SrcName, opts);
+
+                line.push_str(&margin);
+                if opts.color {
+                    line.push_str(&add_color(&padded, color));
+                } else {
+                    line.push_str(&padded);
+                }
+            }
+
+            if opts.color {
+                line
+            } else {
+                line.trim_end().to_string()
+            }
+        })
+        .collect();
+
+    lines.join("\n")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::{ContentType, FileRowInfo, RenderedCols, StatResult};
+    use crate::types::{fixtures, RenderedCols};
 
     fn make_test_row(fname: &str, ftype: crate::types::FileType) -> FileRow {
-        let info = FileRowInfo {
-            fname: String::from(fname),
-            ftype,
-            stat_res: StatResult {
-                st_mode: 0o644,
-                st_mtime: 1704067200,
-                st_uid: 1000,
-                st_gid: 1000,
-                st_size: 1024,
-            },
-            content_type: ContentType::Text,
-            time_epoch: String::from("1704067200"),
+        let mut row = fixtures::make_test_row(fname, ftype);
+        row.render = RenderedCols {
+            acls: String::from("644 4"),
+            owner: String::from("user:group"),
+            filetype: String::from("t"),
+            size: String::from("1,024"),
+            timeiso: String::from("2024-01-01 00:00:00"),
+            gitstatus: String::from("--"),
+            srcname: String::from(fname),
+            targetname: String::from(" "),
+            preview: String::from("content"),
+            tree: String::new(),
+            dimmed: false,
         };
-        FileRow {
-            info,
-            render: RenderedCols {
-                acls: String::from("644 4"),
-                owner: String::from("user:group"),
-                filetype: String::from("t"),
-                size: String::from("1,024"),
-                timeiso: String::from("2024-01-01 00:00:00"),
-                srcname: String::from(fname),
-                targetname: String::from(" "),
-                preview: String::from("content"),
-            },
-        }
+        row
     }
 
     #[test]
@@ -195,6 +344,7 @@ mod tests {
         assert_eq!(cols[1], ColType::Owner);
         assert_eq!(cols[2], ColType::FileType);
         assert_eq!(cols[7], ColType::Preview);
+        assert!(!cols.contains(&ColType::GitStatus));
     }
 
     #[test]
@@ -227,15 +377,289 @@ mod tests {
     #[test]
     fn test_render_rows_empty() {
         let rows: Vec<FileRow> = vec![];
-        let result = render_rows(&rows, false);
+        let result = render_rows(&rows, &get_cols_listing(false), &RenderOptions::default());
         assert_eq!(result, "");
     }
 
     #[test]
     fn test_render_rows_contains_margin() {
-        let rows = vec![make_test_row("test.txt", crate::types::FileType::File)];
+        let rows = [make_test_row("test.txt", crate::types::FileType::File)];
         let paddings = ColPaddings::default();
-        let result = render_cols(&rows[0], &paddings, false);
+        let result = render_cols(
+            &rows[0],
+            &paddings,
+            &get_cols_listing(false),
+            &RenderOptions::default(),
+        );
         assert!(result.starts_with("  "));
     }
+
+    #[test]
+    fn test_get_col_paddings_includes_tree() {
+        let mut row = make_test_row("test.txt", crate::types::FileType::File);
+        row.render.tree = String::from("│   └── ");
+        let paddings = get_col_paddings(&[row]);
+        assert_eq!(paddings.srcname, 16);
+    }
+
+    #[test]
+    fn test_render_cols_tree_prefix_uncolored() {
+        let mut row = make_test_row("test.txt", crate::types::FileType::File);
+        row.render.tree = String::from("└── ");
+        let paddings = get_col_paddings(std::slice::from_ref(&row));
+        let result = render_cols(
+            &row,
+            &paddings,
+            &get_cols_listing(false),
+            &RenderOptions::default(),
+        );
+        assert!(result.contains("└── \x1b["));
+    }
+
+    #[test]
+    fn test_parse_cols_listing_order() {
+        let cols = parse_cols_listing("size,time,name,owner").unwrap();
+        assert_eq!(
+            cols,
+            [ColType::Size, ColType::TimeIso, ColType::SrcName, ColType::Owner]
+        );
+    }
+
+    #[test]
+    fn test_parse_cols_listing_trims() {
+        let cols = parse_cols_listing(" owner , name ").unwrap();
+        assert_eq!(cols, [ColType::Owner, ColType::SrcName]);
+    }
+
+    #[test]
+    fn test_parse_cols_listing_unknown() {
+        assert!(parse_cols_listing("size,bogus").is_err());
+    }
+
+    #[test]
+    fn test_parse_cols_listing_empty() {
+        assert_eq!(parse_cols_listing(""), Err(String::from("no columns given")));
+        assert_eq!(parse_cols_listing("  "), Err(String::from("no columns given")));
+    }
+
+    #[test]
+    fn test_render_cols_selected_only() {
+        let row = make_test_row("test.txt", crate::types::FileType::File);
+        let paddings = get_col_paddings(std::slice::from_ref(&row));
+        let result = render_cols(
+            &row,
+            &paddings,
+            &[ColType::Owner, ColType::SrcName],
+            &RenderOptions::default(),
+        );
+        assert!(result.contains("user:group"));
+        assert!(result.contains("test.txt"));
+        assert!(!result.contains("1,024"));
+        assert!(result.find("user:group") < result.find("test.txt"));
+    }
+
+    #[test]
+    fn test_render_cols_no_color() {
+        let row = make_test_row("test.txt", crate::types::FileType::File);
+        let paddings = ColPaddings {
+            srcname: 20,
+            ..get_col_paddings(std::slice::from_ref(&row))
+        };
+        let opts = RenderOptions {
+            color: false,
+            ..RenderOptions::default()
+        };
+        let result = render_cols(&row, &paddings, &[ColType::Size, ColType::SrcName], &opts);
+        assert!(!result.contains('\x1b'));
+        assert!(result.ends_with("test.txt"));
+    }
+
+    fn no_color_width(width: usize) -> RenderOptions {
+        RenderOptions {
+            color: false,
+            width: Some(width),
+            ..RenderOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_get_row_width() {
+        let paddings = ColPaddings {
+            size: 5,
+            srcname: 10,
+            ..ColPaddings::default()
+        };
+        assert_eq!(get_row_width(&paddings, &[ColType::Size, ColType::SrcName]), 19);
+        assert_eq!(get_row_width(&paddings, &[ColType::Preview]), 3);
+    }
+
+    #[test]
+    fn test_fit_paddings_shrinks_preview_first() {
+        let mut paddings = ColPaddings {
+            srcname: 20,
+            targetname: 20,
+            preview: 30,
+            ..ColPaddings::default()
+        };
+        let cols = [ColType::SrcName, ColType::TargetName, ColType::Preview];
+        fit_paddings(&mut paddings, &cols, 66);
+        assert_eq!(paddings.preview, 20);
+        assert_eq!(paddings.targetname, 20);
+        assert_eq!(paddings.srcname, 20);
+    }
+
+    #[test]
+    fn test_fit_paddings_then_target_then_name() {
+        let mut paddings = ColPaddings {
+            srcname: 30,
+            targetname: 30,
+            preview: 30,
+            ..ColPaddings::default()
+        };
+        let cols = [ColType::SrcName, ColType::TargetName, ColType::Preview];
+        fit_paddings(&mut paddings, &cols, 40);
+        assert_eq!(paddings.preview, MIN_PREVIEW_WIDTH);
+        assert_eq!(paddings.targetname, MIN_NAME_WIDTH);
+        assert_eq!(paddings.srcname, 21);
+        assert_eq!(get_row_width(&paddings, &cols), 40);
+    }
+
+    #[test]
+    fn test_fit_paddings_leaves_fitting_rows() {
+        let mut paddings = ColPaddings {
+            srcname: 10,
+            preview: 10,
+            ..ColPaddings::default()
+        };
+        fit_paddings(&mut paddings, &[ColType::SrcName, ColType::Preview], 80);
+        assert_eq!(paddings.preview, 10);
+        assert_eq!(paddings.srcname, 10);
+    }
+
+    #[test]
+    fn test_fit_paddings_keeps_tree_prefix() {
+        let mut paddings = ColPaddings {
+            srcname: 60,
+            tree: 40,
+            ..ColPaddings::default()
+        };
+        fit_paddings(&mut paddings, &[ColType::SrcName], 20);
+        assert_eq!(paddings.srcname, 40 + MIN_NAME_WIDTH);
+    }
+
+    #[test]
+    fn test_render_rows_deep_tree_names() {
+        let fname = "a_long_file_name_deep_in_the_tree.txt";
+        let mut row = make_test_row(fname, crate::types::FileType::File);
+        row.render.tree = "│   ".repeat(10) + "└── ";
+        let result = render_rows(&[row], &[ColType::SrcName], &no_color_width(30));
+        let name = result.trim_start().trim_start_matches(['│', '└', '─', ' ']);
+        assert_eq!(name.chars().count(), MIN_NAME_WIDTH);
+        assert!(name.contains("..."));
+    }
+
+    #[test]
+    fn test_render_rows_fits_width() {
+        let name = "a_really_long_file_name_for_testing.txt";
+        let mut row = make_test_row(name, crate::types::FileType::File);
+        row.render.preview = "x".repeat(50);
+        let cols = [ColType::Size, ColType::SrcName, ColType::Preview];
+        let result = render_rows(&[row], &cols, &no_color_width(40));
+        assert!(result.chars().count() <= 40);
+        assert!(result.contains("..."));
+        assert!(result.ends_with(".txt  x"));
+    }
+
+    #[test]
+    fn test_render_rows_fits_width_wide_chars() {
+        let fname = "日本語のとても長いファイル名です.txt";
+        let mut wide = make_test_row(fname, crate::types::FileType::File);
+        wide.render.preview = "表".repeat(30);
+        let narrow = make_test_row("a.txt", crate::types::FileType::File);
+        let cols = [ColType::Size, ColType::SrcName, ColType::Preview];
+        let result = render_rows(&[wide, narrow], &cols, &no_color_width(40));
+        for line in result.lines() {
+            assert!(visible_width(line) <= 40, "{:?}", line);
+        }
+        assert!(result.contains("..."));
+    }
+
+    #[test]
+    fn test_render_rows_unbounded_without_width() {
+        let mut row = make_test_row("name.txt", crate::types::FileType::File);
+        row.render.preview = "x".repeat(200);
+        let opts = RenderOptions {
+            color: false,
+            ..RenderOptions::default()
+        };
+        let result = render_rows(&[row], &[ColType::SrcName, ColType::Preview], &opts);
+        assert!(result.ends_with(&"x".repeat(200)));
+    }
+
+    #[test]
+    fn test_get_grid_layout_single_row() {
+        let (rows, widths) = get_grid_layout(&[3, 5, 4], 80);
+        assert_eq!(rows, 1);
+        assert_eq!(widths, [3, 5, 4]);
+    }
+
+    #[test]
+    fn test_get_grid_layout_column_major() {
+        let (rows, widths) = get_grid_layout(&[10, 2, 3, 10, 4], 30);
+        assert_eq!(rows, 2);
+        assert_eq!(widths, [10, 10, 4]);
+    }
+
+    #[test]
+    fn test_get_grid_layout_too_narrow() {
+        let (rows, widths) = get_grid_layout(&[30, 40], 20);
+        assert_eq!(rows, 2);
+        assert_eq!(widths, [40]);
+    }
+
+    #[test]
+    fn test_get_grid_layout_many_entries() {
+        let widths = vec![6; 10_000];
+        let (rows, col_widths) = get_grid_layout(&widths, 80);
+        assert_eq!(col_widths, [6; 10]);
+        assert_eq!(rows, 1000);
+    }
+
+    #[test]
+    fn test_get_grid_layout_empty() {
+        assert_eq!(get_grid_layout(&[], 80), (0, vec![0]));
+    }
+
+    #[test]
+    fn test_render_grid_column_major() {
+        let rows: Vec<FileRow> = ["a", "bb", "c", "dd", "e"]
+            .iter()
+            .map(|name| make_test_row(name, crate::types::FileType::File))
+            .collect();
+        let opts = RenderOptions {
+            color: false,
+            ..RenderOptions::default()
+        };
+        assert_eq!(render_grid(&rows, &opts, 12), "  a   c   e\n  bb  dd");
+    }
+
+    #[test]
+    fn test_render_grid_wide_chars() {
+        let rows: Vec<FileRow> = ["日本", "b", "語", "d"]
+            .iter()
+            .map(|name| make_test_row(name, crate::types::FileType::File))
+            .collect();
+        let opts = RenderOptions {
+            color: false,
+            ..RenderOptions::default()
+        };
+        assert_eq!(render_grid(&rows, &opts, 12), "  日本  語\n  b     d");
+    }
+
+    #[test]
+    fn test_render_grid_colors_names() {
+        let rows = [make_test_row("dir/", crate::types::FileType::Directory)];
+        let result = render_grid(&rows, &RenderOptions::default(), 80);
+        assert!(result.contains("\x1b[91mdir/"));
+    }
 }
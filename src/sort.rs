@@ -0,0 +1,263 @@
+use std::cmp::Ordering;
+use std::path::Path;
+
+use crate::permissions::UserGroupCache;
+use crate::types::{Args, ContentType, FileRow, FileRowInfo, SortKey, TimeField};
+
+pub fn parse_sort_keys(spec: &str) -> Result<Vec<SortKey>, String> {
+    if spec.trim().is_empty() {
+        return Err(String::from("no sort keys given"));
+    }
+
+    spec.split(',')
+        .map(|key| key.trim().parse::<SortKey>())
+        .collect()
+}
+
+fn name_key(info: &FileRowInfo) -> String {
+    info.fname.to_lowercase()
+}
+
+fn ext_key(info: &FileRowInfo) -> String {
+    Path::new(&info.fname)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+fn type_rank(info: &FileRowInfo) -> u8 {
+    match info.content_type {
+        ContentType::Directory => 0,
+        ContentType::Text => 1,
+        ContentType::BinaryExecutable => 2,
+        ContentType::BinaryOther => 3,
+        ContentType::Empty => 4,
+        ContentType::Other => 5,
+        ContentType::NotReadable => 6,
+        ContentType::Unknown => 7,
+    }
+}
+
+// Size and time put the largest and newest entries first, like `ls -S` and `ls -t`.
+// Time follows the shown field; entries without a birth time go last.
+fn compare_key(
+    a: &FileRowInfo,
+    b: &FileRowInfo,
+    key: SortKey,
+    field: TimeField,
+    cache: &UserGroupCache,
+) -> Ordering {
+    match key {
+        SortKey::Name => name_key(a).cmp(&name_key(b)),
+        SortKey::Size => b.stat_res.st_size.cmp(&a.stat_res.st_size),
+        SortKey::Time => b.stat_res.get_time(field).cmp(&a.stat_res.get_time(field)),
+        SortKey::Ext => ext_key(a).cmp(&ext_key(b)),
+        SortKey::Type => type_rank(a).cmp(&type_rank(b)),
+        SortKey::Owner => cache
+            .get_user_name(a.stat_res.st_uid)
+            .cmp(&cache.get_user_name(b.stat_res.st_uid)),
+    }
+}
+
+fn compare_rows(a: &FileRow, b: &FileRow, args: &Args, cache: &UserGroupCache) -> Ordering {
+    let field = args.time.field;
+    let ordering = args
+        .sort
+        .iter()
+        .map(|&key| compare_key(&a.info, &b.info, key, field, cache))
+        .find(|o| o.is_ne())
+        .unwrap_or_else(|| compare_key(&a.info, &b.info, SortKey::Name, field, cache));
+
+    if args.reverse {
+        ordering.reverse()
+    } else {
+        ordering
+    }
+}
+
+pub fn sort_rows(rows: &mut [FileRow], args: &Args, cache: &UserGroupCache) {
+    rows.sort_by(|a, b| {
+        let a_is_dir = a.info.is_dir();
+        let b_is_dir = b.info.is_dir();
+
+        match (args.dirs_first, a_is_dir, b_is_dir) {
+            (true, true, false) => Ordering::Less,
+            (true, false, true) => Ordering::Greater,
+            _ => compare_rows(a, b, args, cache),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::fixtures::make_test_row;
+    use crate::types::FileType;
+
+    fn sorted_names(rows: &mut [FileRow], args: &Args) -> Vec<String> {
+        let cache = UserGroupCache::new();
+        sort_rows(rows, args, &cache);
+        rows.iter().map(|r| r.info.fname.clone()).collect()
+    }
+
+    #[test]
+    fn test_sort_rows_dirs_first() {
+        let mut rows = vec![
+            make_test_row("file.txt", FileType::File),
+            make_test_row("dir", FileType::Directory),
+        ];
+        sort_rows(&mut rows, &Args::default(), &UserGroupCache::new());
+        assert_eq!(rows[0].info.ftype, FileType::Directory);
+        assert_eq!(rows[1].info.ftype, FileType::File);
+    }
+
+    #[test]
+    fn test_sort_rows_alphabetical() {
+        let mut rows = vec![
+            make_test_row("zebra", FileType::File),
+            make_test_row("apple", FileType::File),
+            make_test_row("mango", FileType::File),
+        ];
+        sort_rows(&mut rows, &Args::default(), &UserGroupCache::new());
+        assert!(rows[0].info.fname.contains("apple"));
+        assert!(rows[1].info.fname.contains("mango"));
+        assert!(rows[2].info.fname.contains("zebra"));
+    }
+
+    #[test]
+    fn test_sort_rows_case_insensitive() {
+        let mut rows = vec![
+            make_test_row("Zebra", FileType::File),
+            make_test_row("apple", FileType::File),
+        ];
+        sort_rows(&mut rows, &Args::default(), &UserGroupCache::new());
+        assert!(rows[0].info.fname.contains("apple"));
+        assert!(rows[1].info.fname.contains("Zebra"));
+    }
+
+    #[test]
+    fn test_sort_rows_dirs_alphabetical() {
+        let mut rows = vec![
+            make_test_row("zdir", FileType::Directory),
+            make_test_row("adir", FileType::Directory),
+            make_test_row("file", FileType::File),
+        ];
+        sort_rows(&mut rows, &Args::default(), &UserGroupCache::new());
+        assert!(rows[0].info.fname.contains("adir"));
+        assert!(rows[1].info.fname.contains("zdir"));
+        assert!(rows[2].info.fname.contains("file"));
+    }
+
+    #[test]
+    fn test_sort_rows_mtime_newest_first() {
+        let mut old = make_test_row("old.log", FileType::File);
+        let mut new = make_test_row("new.log", FileType::File);
+        old.info.stat_res.st_mtime = 100;
+        new.info.stat_res.st_mtime = 200;
+        let args = Args {
+            sort: vec![SortKey::Time],
+            ..Args::default()
+        };
+        assert_eq!(sorted_names(&mut [old, new], &args), ["new.log", "old.log"]);
+    }
+
+    #[test]
+    fn test_sort_rows_time_follows_time_field() {
+        let mut a = make_test_row("a", FileType::File);
+        let mut b = make_test_row("b", FileType::File);
+        a.info.stat_res.st_mtime = 200;
+        b.info.stat_res.st_ctime = 300;
+        let mut args = Args {
+            sort: vec![SortKey::Time],
+            ..Args::default()
+        };
+        args.time.field = TimeField::Ctime;
+        assert_eq!(sorted_names(&mut [a.clone(), b.clone()], &args), ["b", "a"]);
+
+        args.time.field = TimeField::Btime;
+        a.info.stat_res.st_btime = Some(1);
+        assert_eq!(sorted_names(&mut [b, a], &args), ["a", "b"]);
+    }
+
+    #[test]
+    fn test_sort_rows_size_largest_first() {
+        let mut small = make_test_row("small", FileType::File);
+        let mut big = make_test_row("big", FileType::File);
+        small.info.stat_res.st_size = 1;
+        big.info.stat_res.st_size = 1000;
+        let args = Args {
+            sort: vec![SortKey::Size],
+            ..Args::default()
+        };
+        assert_eq!(sorted_names(&mut [small, big], &args), ["big", "small"]);
+    }
+
+    #[test]
+    fn test_sort_rows_reverse() {
+        let rows = &mut [
+            make_test_row("apple", FileType::File),
+            make_test_row("zebra", FileType::File),
+            make_test_row("dir", FileType::Directory),
+        ];
+        let args = Args {
+            reverse: true,
+            ..Args::default()
+        };
+        assert_eq!(sorted_names(rows, &args), ["dir", "zebra", "apple"]);
+    }
+
+    #[test]
+    fn test_sort_rows_no_dirs_first() {
+        let rows = &mut [
+            make_test_row("zdir", FileType::Directory),
+            make_test_row("apple", FileType::File),
+        ];
+        let args = Args {
+            dirs_first: false,
+            ..Args::default()
+        };
+        assert_eq!(sorted_names(rows, &args), ["apple", "zdir"]);
+    }
+
+    #[test]
+    fn test_sort_rows_ext() {
+        let rows = &mut [
+            make_test_row("b.txt", FileType::File),
+            make_test_row("a.rs", FileType::File),
+            make_test_row("c", FileType::File),
+        ];
+        let args = Args {
+            sort: vec![SortKey::Ext],
+            ..Args::default()
+        };
+        assert_eq!(sorted_names(rows, &args), ["c", "a.rs", "b.txt"]);
+    }
+
+    #[test]
+    fn test_sort_rows_chained_keys() {
+        let mut a = make_test_row("a.bin", FileType::File);
+        let mut b = make_test_row("b.txt", FileType::File);
+        let mut c = make_test_row("c.txt", FileType::File);
+        a.info.content_type = ContentType::BinaryOther;
+        b.info.stat_res.st_mtime = 100;
+        c.info.stat_res.st_mtime = 200;
+        let args = Args {
+            sort: vec![SortKey::Type, SortKey::Time],
+            ..Args::default()
+        };
+        assert_eq!(
+            sorted_names(&mut [a, b, c], &args),
+            ["c.txt", "b.txt", "a.bin"]
+        );
+    }
+
+    #[test]
+    fn test_parse_sort_keys() {
+        assert_eq!(
+            parse_sort_keys("type,mtime").unwrap(),
+            [SortKey::Type, SortKey::Time]
+        );
+        assert!(parse_sort_keys("type,bogus").is_err());
+        assert_eq!(parse_sort_keys(" ").unwrap_err(), "no sort keys given");
+    }
+}
@@ -0,0 +1,94 @@
+use crate::types::ColorMode;
+
+pub fn is_stdout_tty() -> bool {
+    unsafe { libc::isatty(libc::STDOUT_FILENO) == 1 }
+}
+
+// Returns (columns, rows) for the terminal behind `fd`
+pub fn get_terminal_size(fd: libc::c_int) -> Option<(usize, usize)> {
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+
+    if unsafe { libc::ioctl(fd, libc::TIOCGWINSZ, &mut size) } != 0 {
+        return None;
+    }
+
+    if size.ws_col == 0 || size.ws_row == 0 {
+        return None;
+    }
+
+    Some((size.ws_col as usize, size.ws_row as usize))
+}
+
+// None when stdout is not a terminal, which leaves rows unbounded
+pub fn get_output_width() -> Option<usize> {
+    get_terminal_size(libc::STDOUT_FILENO).map(|(cols, _)| cols)
+}
+
+fn is_set(value: Option<&str>) -> bool {
+    value.is_some_and(|v| !v.is_empty())
+}
+
+// An explicit --color wins; otherwise NO_COLOR, then CLICOLOR_FORCE, then the terminal check
+pub fn resolve_color(
+    mode: ColorMode,
+    no_color: Option<&str>,
+    clicolor_force: Option<&str>,
+    tty: bool,
+) -> bool {
+    match mode {
+        ColorMode::Always => true,
+        ColorMode::Never => false,
+        ColorMode::Auto if is_set(no_color) => false,
+        ColorMode::Auto if is_set(clicolor_force) && clicolor_force != Some("0") => true,
+        ColorMode::Auto => tty,
+    }
+}
+
+pub fn use_color(mode: ColorMode) -> bool {
+    let no_color = std::env::var("NO_COLOR").ok();
+    let clicolor_force = std::env::var("CLICOLOR_FORCE").ok();
+    resolve_color(
+        mode,
+        no_color.as_deref(),
+        clicolor_force.as_deref(),
+        is_stdout_tty(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_terminal_size_not_a_tty() {
+        let file = tempfile::tempfile().unwrap();
+        use std::os::unix::io::AsRawFd;
+        assert_eq!(get_terminal_size(file.as_raw_fd()), None);
+    }
+
+    #[test]
+    fn test_resolve_color_explicit_modes() {
+        assert!(resolve_color(ColorMode::Always, Some("1"), None, false));
+        assert!(!resolve_color(ColorMode::Never, None, Some("1"), true));
+    }
+
+    #[test]
+    fn test_resolve_color_auto_follows_tty() {
+        assert!(resolve_color(ColorMode::Auto, None, None, true));
+        assert!(!resolve_color(ColorMode::Auto, None, None, false));
+    }
+
+    #[test]
+    fn test_resolve_color_no_color() {
+        assert!(!resolve_color(ColorMode::Auto, Some("1"), None, true));
+        assert!(!resolve_color(ColorMode::Auto, Some("1"), Some("1"), true));
+        assert!(resolve_color(ColorMode::Auto, Some(""), None, true));
+    }
+
+    #[test]
+    fn test_resolve_color_clicolor_force() {
+        assert!(resolve_color(ColorMode::Auto, None, Some("1"), false));
+        assert!(!resolve_color(ColorMode::Auto, None, Some("0"), false));
+        assert!(!resolve_color(ColorMode::Auto, None, Some(""), false));
+    }
+}
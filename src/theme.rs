@@ -0,0 +1,357 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::colors::{
+    ANSI_BLUE, ANSI_CYAN, ANSI_DARK_GRAY, ANSI_DIM_BLUE, ANSI_GREEN, ANSI_LIGHT_BLUE,
+    ANSI_LIGHT_CYAN, ANSI_LIGHT_GREEN, ANSI_LIGHT_MAGENTA, ANSI_LIGHT_RED, ANSI_LIGHT_YELLOW,
+    ANSI_MAGENTA, ANSI_RED,
+};
+
+// Its keys double as the full set a theme file may set
+const DARK_THEME: [(&str, &str); 28] = [
+    ("acls", ANSI_DARK_GRAY),
+    ("owner", ANSI_DARK_GRAY),
+    ("filetype", ANSI_DARK_GRAY),
+    ("preview", ANSI_DARK_GRAY),
+    ("time", ANSI_BLUE),
+    ("size", ANSI_GREEN),
+    ("size.dir", ANSI_MAGENTA),
+    ("target", ANSI_LIGHT_CYAN),
+    ("target.broken", ANSI_RED),
+    ("name.dir", ANSI_LIGHT_RED),
+    ("name.symlink", ANSI_LIGHT_CYAN),
+    ("name.broken", ANSI_RED),
+    ("name.fifo", ANSI_LIGHT_YELLOW),
+    ("name.socket", ANSI_LIGHT_MAGENTA),
+    ("name.device", ANSI_LIGHT_BLUE),
+    ("name.file", ANSI_LIGHT_GREEN),
+    ("age.hour", ANSI_LIGHT_CYAN),
+    ("age.day", ANSI_CYAN),
+    ("age.week", ANSI_LIGHT_BLUE),
+    ("age.month", ANSI_BLUE),
+    ("age.old", ANSI_DIM_BLUE),
+    ("ignored", "\x1b[2;37m"),
+    ("git.clean", ANSI_DARK_GRAY),
+    ("git.modified", ANSI_LIGHT_YELLOW),
+    ("git.staged", ANSI_LIGHT_GREEN),
+    ("git.untracked", ANSI_LIGHT_MAGENTA),
+    ("git.ignored", ANSI_DARK_GRAY),
+    ("git.conflicted", ANSI_LIGHT_RED),
+];
+
+// Bright colors wash out on white backgrounds, so the light theme sticks to normal ones
+const LIGHT_THEME: [(&str, &str); 28] = [
+    ("acls", ANSI_DARK_GRAY),
+    ("owner", ANSI_DARK_GRAY),
+    ("filetype", ANSI_DARK_GRAY),
+    ("preview", ANSI_DARK_GRAY),
+    ("time", ANSI_BLUE),
+    ("size", ANSI_GREEN),
+    ("size.dir", ANSI_MAGENTA),
+    ("target", ANSI_CYAN),
+    ("target.broken", "\x1b[1;31m"),
+    ("name.dir", "\x1b[1;34m"),
+    ("name.symlink", ANSI_CYAN),
+    ("name.broken", "\x1b[1;31m"),
+    ("name.fifo", "\x1b[33m"),
+    ("name.socket", ANSI_MAGENTA),
+    ("name.device", "\x1b[1;33m"),
+    ("name.file", ANSI_GREEN),
+    ("age.hour", "\x1b[1;34m"),
+    ("age.day", ANSI_BLUE),
+    ("age.week", ANSI_CYAN),
+    ("age.month", ANSI_DARK_GRAY),
+    ("age.old", "\x1b[2;90m"),
+    ("ignored", "\x1b[2;90m"),
+    ("git.clean", ANSI_DARK_GRAY),
+    ("git.modified", "\x1b[33m"),
+    ("git.staged", ANSI_GREEN),
+    ("git.untracked", ANSI_MAGENTA),
+    ("git.ignored", ANSI_DARK_GRAY),
+    ("git.conflicted", "\x1b[1;31m"),
+];
+
+const COLOR_NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+#[derive(Debug, Clone)]
+pub struct Theme {
+    styles: HashMap<&'static str, String>,
+}
+
+impl Theme {
+    fn from_pairs(pairs: &[(&'static str, &str)]) -> Self {
+        let styles = pairs
+            .iter()
+            .map(|&(key, code)| (key, code.to_string()))
+            .collect();
+        Self { styles }
+    }
+
+    pub fn dark() -> Self {
+        Self::from_pairs(&DARK_THEME)
+    }
+
+    pub fn light() -> Self {
+        Self::from_pairs(&LIGHT_THEME)
+    }
+
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::dark()),
+            "light" => Some(Self::light()),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> &str {
+        self.styles.get(key).map(String::as_str).unwrap_or("")
+    }
+
+    // `key = style` lines; an optional `base = light` picks the theme to start from
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut base = Self::dark();
+        let mut overrides: Vec<(&'static str, String)> = Vec::new();
+
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .map(|(k, v)| (k.trim(), v.trim()))
+                .ok_or_else(|| format!("line {}: expected 'key = style'", i + 1))?;
+
+            if key == "base" {
+                base = Self::builtin(value)
+                    .ok_or_else(|| format!("line {}: unknown base theme '{}'", i + 1, value))?;
+                continue;
+            }
+
+            let key = DARK_THEME
+                .iter()
+                .map(|&(k, _)| k)
+                .find(|&k| k == key)
+                .ok_or_else(|| format!("line {}: unknown theme key '{}'", i + 1, key))?;
+            let code = parse_style(value).map_err(|e| format!("line {}: {}", i + 1, e))?;
+            overrides.push((key, code));
+        }
+
+        base.styles.extend(overrides);
+        Ok(base)
+    }
+
+    pub fn load(spec: Option<&str>) -> Result<Self, String> {
+        let path = match spec {
+            Some(name) => match Self::builtin(name) {
+                Some(theme) => return Ok(theme),
+                None => PathBuf::from(name),
+            },
+            None => match get_default_theme_path().filter(|p| p.is_file()) {
+                Some(path) => path,
+                None => return Ok(Self::dark()),
+            },
+        };
+
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("cannot read theme '{}': {}", path.display(), e))?;
+        Self::parse(&content).map_err(|e| format!("theme '{}': {}", path.display(), e))
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+fn get_default_theme_path() -> Option<PathBuf> {
+    let config = match std::env::var_os("XDG_CONFIG_HOME").filter(|v| !v.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(config.join("els").join("theme"))
+}
+
+// Returns the SGR parameters for a color in the foreground (base 30) or background (base 40)
+fn parse_color(token: &str, base: u8) -> Result<String, String> {
+    let name = token.to_lowercase();
+
+    if let Some(hex) = name.strip_prefix('#') {
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("invalid hex color '{}'", token));
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap_or(0);
+        return Ok(format!(
+            "{};2;{};{};{}",
+            base + 8,
+            channel(0),
+            channel(2),
+            channel(4)
+        ));
+    }
+
+    if let Ok(index) = name.parse::<u8>() {
+        return Ok(format!("{};5;{}", base + 8, index));
+    }
+
+    if name == "gray" || name == "grey" {
+        return Ok((base + 60).to_string());
+    }
+
+    let (bright, plain) = match name
+        .strip_prefix("bright-")
+        .or_else(|| name.strip_prefix("light-"))
+    {
+        Some(rest) => (true, rest),
+        None => (false, name.as_str()),
+    };
+
+    let offset = COLOR_NAMES
+        .iter()
+        .position(|&c| c == plain)
+        .ok_or_else(|| format!("unknown color '{}'", token))? as u8;
+
+    Ok(if bright {
+        (base + 60 + offset).to_string()
+    } else {
+        (base + offset).to_string()
+    })
+}
+
+// e.g. `bold #ff8800 on 236`: attributes, a foreground, then `on` and a background
+pub fn parse_style(spec: &str) -> Result<String, String> {
+    let mut params: Vec<String> = Vec::new();
+    let mut tokens = spec.split_whitespace();
+
+    while let Some(token) = tokens.next() {
+        match token.to_lowercase().as_str() {
+            "none" | "default" => {}
+            "bold" => params.push(String::from("1")),
+            "dim" => params.push(String::from("2")),
+            "italic" => params.push(String::from("3")),
+            "underline" => params.push(String::from("4")),
+            "reverse" => params.push(String::from("7")),
+            "on" => {
+                let color = tokens
+                    .next()
+                    .ok_or_else(|| String::from("missing color after 'on'"))?;
+                params.push(parse_color(color, 40)?);
+            }
+            _ => params.push(parse_color(token, 30)?),
+        }
+    }
+
+    if params.is_empty() {
+        return Ok(String::new());
+    }
+
+    Ok(format!("\x1b[{}m", params.join(";")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_builtin_themes_cover_all_keys() {
+        for theme in [Theme::dark(), Theme::light()] {
+            for (key, _) in DARK_THEME {
+                assert!(!theme.get(key).is_empty(), "missing {}", key);
+            }
+        }
+    }
+
+    #[test]
+    fn test_builtin_themes_share_keys() {
+        let dark = DARK_THEME.map(|(key, _)| key);
+        let light = LIGHT_THEME.map(|(key, _)| key);
+        assert_eq!(dark, light);
+    }
+
+    #[test]
+    fn test_builtin_lookup() {
+        assert!(Theme::builtin("dark").is_some());
+        assert!(Theme::builtin("light").is_some());
+        assert!(Theme::builtin("solarized").is_none());
+    }
+
+    #[test]
+    fn test_dark_gray_is_visible() {
+        assert_eq!(Theme::dark().get("owner"), "\x1b[90m");
+    }
+
+    #[test]
+    fn test_parse_style_named() {
+        assert_eq!(parse_style("red").unwrap(), "\x1b[31m");
+        assert_eq!(parse_style("bright-blue").unwrap(), "\x1b[94m");
+        assert_eq!(parse_style("bold light-green").unwrap(), "\x1b[1;92m");
+        assert_eq!(parse_style("gray").unwrap(), "\x1b[90m");
+    }
+
+    #[test]
+    fn test_parse_style_256_and_truecolor() {
+        assert_eq!(parse_style("208").unwrap(), "\x1b[38;5;208m");
+        assert_eq!(parse_style("#ff8800").unwrap(), "\x1b[38;2;255;136;0m");
+    }
+
+    #[test]
+    fn test_parse_style_background() {
+        assert_eq!(parse_style("black on yellow").unwrap(), "\x1b[30;43m");
+        assert_eq!(parse_style("on 236").unwrap(), "\x1b[48;5;236m");
+        assert_eq!(parse_style("on #000000").unwrap(), "\x1b[48;2;0;0;0m");
+    }
+
+    #[test]
+    fn test_parse_style_empty() {
+        assert_eq!(parse_style("none").unwrap(), "");
+        assert_eq!(parse_style("").unwrap(), "");
+    }
+
+    #[test]
+    fn test_parse_style_errors() {
+        assert!(parse_style("purple").is_err());
+        assert!(parse_style("#12345").is_err());
+        assert!(parse_style("256").is_err());
+        assert!(parse_style("red on").is_err());
+    }
+
+    #[test]
+    fn test_theme_parse_overrides_base() {
+        let theme = Theme::parse("# mine\nbase = light\n\nname.dir = bold #ff8800\n").unwrap();
+        assert_eq!(theme.get("name.dir"), "\x1b[1;38;2;255;136;0m");
+        assert_eq!(theme.get("name.file"), Theme::light().get("name.file"));
+    }
+
+    #[test]
+    fn test_theme_parse_errors_name_line() {
+        let err = Theme::parse("size = green\nname.bogus = red\n").unwrap_err();
+        assert!(err.contains("line 2"));
+        assert!(Theme::parse("size green").is_err());
+        assert!(Theme::parse("base = neon").is_err());
+    }
+
+    #[test]
+    fn test_theme_load_builtin_and_file() {
+        assert_eq!(
+            Theme::load(Some("light")).unwrap().get("name.dir"),
+            "\x1b[1;34m"
+        );
+
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "time = italic cyan").unwrap();
+        let path = file.path().to_str().unwrap();
+        assert_eq!(Theme::load(Some(path)).unwrap().get("time"), "\x1b[3;36m");
+    }
+
+    #[test]
+    fn test_theme_load_missing_file() {
+        assert!(Theme::load(Some("/nonexistent/els-theme")).is_err());
+    }
+}
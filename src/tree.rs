@@ -0,0 +1,94 @@
+use crate::types::FileRow;
+
+const TREE_BRANCH: &str = "├── ";
+const TREE_LAST: &str = "└── ";
+const TREE_PIPE: &str = "│   ";
+const TREE_SPACE: &str = "    ";
+
+pub struct TreeNode {
+    pub row: FileRow,
+    pub children: Vec<TreeNode>,
+}
+
+impl TreeNode {
+    pub fn new(row: FileRow) -> Self {
+        Self {
+            row,
+            children: Vec::new(),
+        }
+    }
+}
+
+pub fn flatten_tree(nodes: Vec<TreeNode>, prefix: &str, out: &mut Vec<FileRow>) {
+    let count = nodes.len();
+
+    for (i, node) in nodes.into_iter().enumerate() {
+        let is_last = i + 1 == count;
+        let connector = if is_last { TREE_LAST } else { TREE_BRANCH };
+        let child_prefix = if is_last { TREE_SPACE } else { TREE_PIPE };
+
+        let mut row = node.row;
+        row.render.tree = format!("{}{}", prefix, connector);
+        out.push(row);
+
+        let next_prefix = format!("{}{}", prefix, child_prefix);
+        flatten_tree(node.children, &next_prefix, out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::fixtures::make_test_row;
+    use crate::types::FileType;
+
+    fn make_test_node(fname: &str, children: Vec<TreeNode>) -> TreeNode {
+        TreeNode {
+            row: make_test_row(fname, FileType::File),
+            children,
+        }
+    }
+
+    #[test]
+    fn test_flatten_tree_empty() {
+        let mut out = Vec::new();
+        flatten_tree(Vec::new(), "", &mut out);
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_flatten_tree_single_level() {
+        let nodes = vec![make_test_node("a", vec![]), make_test_node("b", vec![])];
+        let mut out = Vec::new();
+        flatten_tree(nodes, "", &mut out);
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0].render.tree, "├── ");
+        assert_eq!(out[1].render.tree, "└── ");
+    }
+
+    #[test]
+    fn test_flatten_tree_children_follow_parent() {
+        let nodes = vec![
+            make_test_node("dir", vec![make_test_node("dir/inner", vec![])]),
+            make_test_node("file", vec![]),
+        ];
+        let mut out = Vec::new();
+        flatten_tree(nodes, "", &mut out);
+        assert_eq!(out.len(), 3);
+        assert_eq!(out[0].info.fname, "dir");
+        assert_eq!(out[1].info.fname, "dir/inner");
+        assert_eq!(out[2].info.fname, "file");
+    }
+
+    #[test]
+    fn test_flatten_tree_nested_prefixes() {
+        let nodes = vec![
+            make_test_node("dir", vec![make_test_node("dir/inner", vec![])]),
+            make_test_node("last", vec![make_test_node("last/inner", vec![])]),
+        ];
+        let mut out = Vec::new();
+        flatten_tree(nodes, "", &mut out);
+        assert_eq!(out[1].render.tree, "│   └── ");
+        assert_eq!(out[3].render.tree, "    └── ");
+    }
+}
@@ -1,9 +1,21 @@
 #![allow(dead_code)]
 
+use std::str::FromStr;
+
+use crate::filters::NameFilter;
+use crate::ls_colors::LsColors;
+use crate::theme::Theme;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FileType {
     File,
     Directory,
+    Symlink,
+    BrokenSymlink,
+    Fifo,
+    Socket,
+    BlockDevice,
+    CharDevice,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -31,18 +43,73 @@ pub enum ColType {
     FileType,
     Size,
     TimeIso,
+    GitStatus,
     SrcName,
     TargetName,
     Preview,
 }
 
-#[derive(Debug, Clone)]
+impl ColType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColType::Acls => "acls",
+            ColType::Owner => "owner",
+            ColType::FileType => "filetype",
+            ColType::Size => "size",
+            ColType::TimeIso => "time",
+            ColType::GitStatus => "git",
+            ColType::SrcName => "name",
+            ColType::TargetName => "target",
+            ColType::Preview => "preview",
+        }
+    }
+}
+
+impl FromStr for ColType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "acls" => Ok(ColType::Acls),
+            "owner" => Ok(ColType::Owner),
+            "filetype" => Ok(ColType::FileType),
+            "size" => Ok(ColType::Size),
+            "time" => Ok(ColType::TimeIso),
+            "git" => Ok(ColType::GitStatus),
+            "name" => Ok(ColType::SrcName),
+            "target" => Ok(ColType::TargetName),
+            "preview" => Ok(ColType::Preview),
+            _ => Err(format!("unknown column '{}'", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct StatResult {
     pub st_mode: u32,
     pub st_mtime: i64,
     pub st_uid: u32,
     pub st_gid: u32,
     pub st_size: u64,
+    pub st_blocks: u64,
+    pub st_mtime_nsec: i64,
+    pub st_atime: i64,
+    pub st_atime_nsec: i64,
+    pub st_ctime: i64,
+    pub st_ctime_nsec: i64,
+    pub st_btime: Option<i64>,
+    pub st_btime_nsec: i64,
+}
+
+impl StatResult {
+    pub fn get_time(&self, field: TimeField) -> Option<(i64, i64)> {
+        match field {
+            TimeField::Mtime => Some((self.st_mtime, self.st_mtime_nsec)),
+            TimeField::Atime => Some((self.st_atime, self.st_atime_nsec)),
+            TimeField::Ctime => Some((self.st_ctime, self.st_ctime_nsec)),
+            TimeField::Btime => self.st_btime.map(|secs| (secs, self.st_btime_nsec)),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -52,6 +119,46 @@ pub struct FileRowInfo {
     pub stat_res: StatResult,
     pub content_type: ContentType,
     pub time_epoch: String,
+    // What a symlink points at, read once while building the row; None for everything else
+    pub link_stat: Option<StatResult>,
+    pub link_target: Option<String>,
+}
+
+impl FileRowInfo {
+    // Symlinks resolving to a directory behave like one for sizing and sorting
+    pub fn is_dir(&self) -> bool {
+        self.ftype == FileType::Directory || self.content_type == ContentType::Directory
+    }
+
+    // The stat a path-following call like access(2) would see; None for a broken symlink
+    pub fn get_followed_stat(&self) -> Option<&StatResult> {
+        match self.ftype {
+            FileType::Symlink | FileType::BrokenSymlink => self.link_stat.as_ref(),
+            _ => Some(&self.stat_res),
+        }
+    }
+}
+
+// The lookups a listing pays for per entry, from its columns, filters, sort keys and format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InfoNeeds {
+    // Mode, owner, size and times; without it the type comes from the directory entry
+    pub stat: bool,
+    // Following symlinks, to tell broken ones and links to directories apart
+    pub link_stat: bool,
+    // The text a symlink points at
+    pub link_target: bool,
+    // Opening regular files to tell text, executables and other binaries apart
+    pub content: bool,
+}
+
+impl InfoNeeds {
+    pub const ALL: Self = Self {
+        stat: true,
+        link_stat: true,
+        link_target: true,
+        content: true,
+    };
 }
 
 #[derive(Debug, Clone, Default)]
@@ -61,9 +168,12 @@ pub struct RenderedCols {
     pub filetype: String,
     pub size: String,
     pub timeiso: String,
+    pub gitstatus: String,
     pub srcname: String,
     pub targetname: String,
     pub preview: String,
+    pub tree: String,
+    pub dimmed: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -85,24 +195,314 @@ pub struct ColPaddings {
     pub filetype: usize,
     pub size: usize,
     pub timeiso: usize,
+    pub gitstatus: usize,
     pub srcname: usize,
+    // The longest tree prefix, already counted in srcname
+    pub tree: usize,
     pub targetname: usize,
     pub preview: usize,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Ndjson,
+    Csv,
+    Tsv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "ndjson" => Ok(OutputFormat::Ndjson),
+            "csv" => Ok(OutputFormat::Csv),
+            "tsv" => Ok(OutputFormat::Tsv),
+            _ => Err(format!("unknown format '{}'", s)),
+        }
+    }
+}
+
+// What `--gitignore` does with ignored entries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IgnoreMode {
+    Hide,
+    Dim,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for ColorMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ColorMode::Auto),
+            "always" => Ok(ColorMode::Always),
+            "never" => Ok(ColorMode::Never),
+            _ => Err(format!("unknown color mode '{}'", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Name,
+    Size,
+    // Whichever field --time-field shows
+    Time,
+    Ext,
+    Type,
+    Owner,
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "name" => Ok(SortKey::Name),
+            "size" => Ok(SortKey::Size),
+            "time" | "mtime" => Ok(SortKey::Time),
+            "ext" => Ok(SortKey::Ext),
+            "type" => Ok(SortKey::Type),
+            "owner" => Ok(SortKey::Owner),
+            _ => Err(format!("unknown sort key '{}'", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizeFormat {
+    Bytes,
+    Human,
+    Si,
+    Iec,
+    Blocks,
+}
+
+impl FromStr for SizeFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bytes" => Ok(SizeFormat::Bytes),
+            "human" => Ok(SizeFormat::Human),
+            "si" => Ok(SizeFormat::Si),
+            "iec" => Ok(SizeFormat::Iec),
+            "blocks" => Ok(SizeFormat::Blocks),
+            _ => Err(format!("unknown size format '{}'", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimeStyle {
+    LongIso,
+    Iso,
+    Rfc3339,
+    Relative,
+    Epoch,
+    Custom(String),
+}
+
+impl FromStr for TimeStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "long-iso" => Ok(TimeStyle::LongIso),
+            "iso" => Ok(TimeStyle::Iso),
+            "rfc3339" => Ok(TimeStyle::Rfc3339),
+            "relative" => Ok(TimeStyle::Relative),
+            "epoch" => Ok(TimeStyle::Epoch),
+            _ => match s.strip_prefix('+') {
+                Some(fmt) if fmt.contains('\0') => {
+                    Err(String::from("time style cannot contain a NUL byte"))
+                }
+                Some(fmt) if !fmt.is_empty() => Ok(TimeStyle::Custom(fmt.to_string())),
+                _ => Err(format!("unknown time style '{}'", s)),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeField {
+    Mtime,
+    Atime,
+    Ctime,
+    Btime,
+}
+
+impl FromStr for TimeField {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mtime" => Ok(TimeField::Mtime),
+            "atime" => Ok(TimeField::Atime),
+            "ctime" => Ok(TimeField::Ctime),
+            "btime" => Ok(TimeField::Btime),
+            _ => Err(format!("unknown time field '{}'", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeOptions {
+    pub style: TimeStyle,
+    pub field: TimeField,
+    pub utc: bool,
+}
+
+impl Default for TimeOptions {
+    fn default() -> Self {
+        Self {
+            style: TimeStyle::LongIso,
+            field: TimeField::Mtime,
+            utc: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    pub time: TimeOptions,
+    pub now: i64,
+    pub ls_colors: Option<LsColors>,
+    pub theme: Theme,
+    pub color: bool,
+    pub width: Option<usize>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            time: TimeOptions::default(),
+            now: 0,
+            ls_colors: None,
+            theme: Theme::default(),
+            color: true,
+            width: None,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Args {
-    pub start_path: String,
+    pub start_paths: Vec<String>,
     pub filter: Option<String>,
     pub full: bool,
+    pub tree: bool,
+    pub depth: Option<usize>,
+    pub grid: bool,
+    pub format: OutputFormat,
+    pub columns: Option<Vec<ColType>>,
+    pub sort: Vec<SortKey>,
+    pub reverse: bool,
+    pub dirs_first: bool,
+    pub broken_links: bool,
+    pub globs: Vec<String>,
+    pub regexes: Vec<String>,
+    pub exclude_globs: Vec<String>,
+    pub exclude_regexes: Vec<String>,
+    pub case_sensitive: bool,
+    pub hide_dotfiles: bool,
+    pub hide: Vec<String>,
+    // Compiled from the pattern fields above once they are all parsed
+    pub names: NameFilter,
+    pub gitignore: Option<IgnoreMode>,
+    pub larger_than: Option<u64>,
+    pub smaller_than: Option<u64>,
+    pub newer_than: Option<i64>,
+    pub older_than: Option<i64>,
+    pub owner: Option<String>,
+    pub types: Vec<char>,
+    pub size_format: SizeFormat,
+    pub time: TimeOptions,
+    pub theme: Theme,
+    pub color: ColorMode,
+    pub no_pager: bool,
 }
 
 impl Default for Args {
     fn default() -> Self {
         Self {
-            start_path: String::from("./"),
+            start_paths: vec![String::from("./")],
             filter: None,
             full: false,
+            tree: false,
+            depth: None,
+            grid: false,
+            format: OutputFormat::Text,
+            columns: None,
+            sort: vec![SortKey::Name],
+            reverse: false,
+            dirs_first: true,
+            broken_links: false,
+            globs: Vec::new(),
+            regexes: Vec::new(),
+            exclude_globs: Vec::new(),
+            exclude_regexes: Vec::new(),
+            case_sensitive: false,
+            hide_dotfiles: false,
+            hide: Vec::new(),
+            names: NameFilter::default(),
+            gitignore: None,
+            larger_than: None,
+            smaller_than: None,
+            newer_than: None,
+            older_than: None,
+            owner: None,
+            types: Vec::new(),
+            size_format: SizeFormat::Bytes,
+            time: TimeOptions::default(),
+            theme: Theme::default(),
+            color: ColorMode::Auto,
+            no_pager: false,
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod fixtures {
+    use super::*;
+
+    // One row shape for every module's tests; tweak fields on the result rather than copying this
+    pub fn make_test_info(fname: &str, ftype: FileType, content_type: ContentType) -> FileRowInfo {
+        FileRowInfo {
+            fname: String::from(fname),
+            ftype,
+            stat_res: StatResult {
+                st_mode: 0o644,
+                st_mtime: 1704067200,
+                st_uid: 1000,
+                st_gid: 1000,
+                st_size: 1024,
+                st_blocks: 8,
+                ..StatResult::default()
+            },
+            content_type,
+            time_epoch: String::from("1704067200"),
+            link_stat: None,
+            link_target: None,
+        }
+    }
+
+    pub fn make_test_row(fname: &str, ftype: FileType) -> FileRow {
+        FileRow {
+            info: make_test_info(fname, ftype, ContentType::Text),
+            render: RenderedCols::default(),
         }
     }
 }
@@ -110,11 +510,12 @@ impl Default for Args {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::fixtures::make_test_info;
 
     #[test]
-    fn test_args_default_start_path() {
+    fn test_args_default_start_paths() {
         let args = Args::default();
-        assert_eq!(args.start_path, "./");
+        assert_eq!(args.start_paths, ["./"]);
     }
 
     #[test]
@@ -129,6 +530,137 @@ mod tests {
         assert!(!args.full);
     }
 
+    #[test]
+    fn test_args_default_tree() {
+        let args = Args::default();
+        assert!(!args.tree);
+        assert!(args.depth.is_none());
+    }
+
+    #[test]
+    fn test_args_default_format() {
+        let args = Args::default();
+        assert_eq!(args.format, OutputFormat::Text);
+    }
+
+    #[test]
+    fn test_output_format_from_str() {
+        assert_eq!("json".parse::<OutputFormat>(), Ok(OutputFormat::Json));
+        assert_eq!("ndjson".parse::<OutputFormat>(), Ok(OutputFormat::Ndjson));
+        assert_eq!("text".parse::<OutputFormat>(), Ok(OutputFormat::Text));
+        assert_eq!("csv".parse::<OutputFormat>(), Ok(OutputFormat::Csv));
+        assert_eq!("tsv".parse::<OutputFormat>(), Ok(OutputFormat::Tsv));
+        assert!("xml".parse::<OutputFormat>().is_err());
+    }
+
+    #[test]
+    fn test_color_mode_from_str() {
+        assert_eq!("auto".parse::<ColorMode>(), Ok(ColorMode::Auto));
+        assert_eq!("always".parse::<ColorMode>(), Ok(ColorMode::Always));
+        assert_eq!("never".parse::<ColorMode>(), Ok(ColorMode::Never));
+        assert!("yes".parse::<ColorMode>().is_err());
+        assert_eq!(Args::default().color, ColorMode::Auto);
+    }
+
+    #[test]
+    fn test_args_default_sort() {
+        let args = Args::default();
+        assert_eq!(args.sort, [SortKey::Name]);
+        assert!(!args.reverse);
+        assert!(args.dirs_first);
+    }
+
+    #[test]
+    fn test_args_default_broken_links() {
+        let args = Args::default();
+        assert!(!args.broken_links);
+    }
+
+    #[test]
+    fn test_args_default_name_patterns() {
+        let args = Args::default();
+        assert!(args.globs.is_empty());
+        assert!(args.regexes.is_empty());
+        assert!(args.exclude_globs.is_empty());
+        assert!(args.exclude_regexes.is_empty());
+        assert!(!args.case_sensitive);
+        assert!(!args.hide_dotfiles);
+        assert!(args.hide.is_empty());
+        assert_eq!(args.gitignore, None);
+    }
+
+    #[test]
+    fn test_args_default_predicates() {
+        let args = Args::default();
+        assert!(args.larger_than.is_none());
+        assert!(args.smaller_than.is_none());
+        assert!(args.newer_than.is_none());
+        assert!(args.older_than.is_none());
+        assert!(args.owner.is_none());
+        assert!(args.types.is_empty());
+    }
+
+    #[test]
+    fn test_size_format_from_str() {
+        assert_eq!("human".parse::<SizeFormat>(), Ok(SizeFormat::Human));
+        assert_eq!("iec".parse::<SizeFormat>(), Ok(SizeFormat::Iec));
+        assert_eq!("blocks".parse::<SizeFormat>(), Ok(SizeFormat::Blocks));
+        assert!("kb".parse::<SizeFormat>().is_err());
+        assert_eq!(Args::default().size_format, SizeFormat::Bytes);
+    }
+
+    #[test]
+    fn test_time_style_from_str() {
+        assert_eq!("iso".parse::<TimeStyle>(), Ok(TimeStyle::Iso));
+        assert_eq!("rfc3339".parse::<TimeStyle>(), Ok(TimeStyle::Rfc3339));
+        assert_eq!("long-iso".parse::<TimeStyle>(), Ok(TimeStyle::LongIso));
+        assert_eq!("relative".parse::<TimeStyle>(), Ok(TimeStyle::Relative));
+        assert_eq!("epoch".parse::<TimeStyle>(), Ok(TimeStyle::Epoch));
+        assert_eq!(
+            "+%H:%M".parse::<TimeStyle>(),
+            Ok(TimeStyle::Custom(String::from("%H:%M")))
+        );
+        assert!("+".parse::<TimeStyle>().is_err());
+        assert!("+%H\0%M".parse::<TimeStyle>().is_err());
+        assert!("full".parse::<TimeStyle>().is_err());
+    }
+
+    #[test]
+    fn test_time_field_from_str() {
+        assert_eq!("ctime".parse::<TimeField>(), Ok(TimeField::Ctime));
+        assert_eq!("btime".parse::<TimeField>(), Ok(TimeField::Btime));
+        assert!("birth".parse::<TimeField>().is_err());
+    }
+
+    #[test]
+    fn test_args_default_time() {
+        let args = Args::default();
+        assert_eq!(args.time.style, TimeStyle::LongIso);
+        assert_eq!(args.time.field, TimeField::Mtime);
+        assert!(!args.time.utc);
+    }
+
+    #[test]
+    fn test_stat_result_get_time() {
+        let stat_res = StatResult {
+            st_mtime: 10,
+            st_mtime_nsec: 5,
+            st_ctime: 20,
+            ..StatResult::default()
+        };
+        assert_eq!(stat_res.get_time(TimeField::Mtime), Some((10, 5)));
+        assert_eq!(stat_res.get_time(TimeField::Ctime), Some((20, 0)));
+        assert_eq!(stat_res.get_time(TimeField::Btime), None);
+    }
+
+    #[test]
+    fn test_sort_key_from_str() {
+        assert_eq!("time".parse::<SortKey>(), Ok(SortKey::Time));
+        assert_eq!("mtime".parse::<SortKey>(), Ok(SortKey::Time));
+        assert_eq!("ext".parse::<SortKey>(), Ok(SortKey::Ext));
+        assert!("date".parse::<SortKey>().is_err());
+    }
+
     #[test]
     fn test_filetype_eq() {
         assert_eq!(FileType::File, FileType::File);
@@ -140,6 +672,35 @@ mod tests {
         assert_ne!(FileType::File, FileType::Directory);
     }
 
+    #[test]
+    fn test_filetype_special_variants() {
+        assert_ne!(FileType::Symlink, FileType::BrokenSymlink);
+        assert_ne!(FileType::Fifo, FileType::Socket);
+        assert_ne!(FileType::BlockDevice, FileType::CharDevice);
+    }
+
+    #[test]
+    fn test_fileinfo_is_dir() {
+        assert!(make_test_info("test", FileType::Directory, ContentType::Directory).is_dir());
+        assert!(make_test_info("test", FileType::Symlink, ContentType::Directory).is_dir());
+        assert!(!make_test_info("test", FileType::Symlink, ContentType::Text).is_dir());
+        assert!(!make_test_info("test", FileType::Fifo, ContentType::Other).is_dir());
+    }
+
+    #[test]
+    fn test_fileinfo_followed_stat() {
+        let file = make_test_info("test", FileType::File, ContentType::Text);
+        assert_eq!(file.get_followed_stat().unwrap().st_size, 1024);
+
+        let mut link = make_test_info("test", FileType::Symlink, ContentType::Text);
+        assert!(link.get_followed_stat().is_none());
+        link.link_stat = Some(StatResult {
+            st_size: 7,
+            ..StatResult::default()
+        });
+        assert_eq!(link.get_followed_stat().unwrap().st_size, 7);
+    }
+
     #[test]
     fn test_contenttype_variants() {
         assert_ne!(ContentType::Directory, ContentType::Text);
@@ -160,6 +721,39 @@ mod tests {
         assert_eq!(ColType::SrcName, ColType::SrcName);
     }
 
+    #[test]
+    fn test_coltype_names() {
+        assert_eq!(ColType::Size.name(), "size");
+        assert_eq!(ColType::TimeIso.name(), "time");
+        assert_eq!(ColType::SrcName.name(), "name");
+        assert_eq!(ColType::TargetName.name(), "target");
+    }
+
+    #[test]
+    fn test_coltype_from_str_roundtrip() {
+        let all = [
+            ColType::Acls,
+            ColType::Owner,
+            ColType::FileType,
+            ColType::Size,
+            ColType::TimeIso,
+            ColType::GitStatus,
+            ColType::SrcName,
+            ColType::TargetName,
+            ColType::Preview,
+        ];
+        for col in all {
+            assert_eq!(col.name().parse::<ColType>(), Ok(col));
+        }
+        assert!("bogus".parse::<ColType>().is_err());
+    }
+
+    #[test]
+    fn test_args_default_columns() {
+        let args = Args::default();
+        assert!(args.columns.is_none());
+    }
+
     #[test]
     fn test_colpaddings_default() {
         let paddings = ColPaddings::default();
@@ -184,5 +778,6 @@ mod tests {
         assert_eq!(cols.srcname, "");
         assert_eq!(cols.targetname, "");
         assert_eq!(cols.preview, "");
+        assert_eq!(cols.tree, "");
     }
 }
@@ -1,11 +1,78 @@
-pub fn format_timestamp(secs: i64) -> String {
+use std::path::{Component, Path};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use regex_lite::Regex;
+
+use crate::types::TimeStyle;
+
+const ESC: char = '\x1b';
+const ZONEINFO_DIR: &str = "/usr/share/zoneinfo";
+const STRFTIME_MAX_LEN: usize = 64 * 1024;
+
+pub fn now_epoch() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+// Applied before any time is formatted; localtime_r reads TZ the first time it runs,
+// and libc has no tzset binding for unix targets to force it sooner
+pub fn set_timezone(tz: &str) {
+    std::env::set_var("TZ", tz);
+}
+
+// A zone name under the zoneinfo directory, an absolute zone file, or a POSIX TZ string
+// such as `EST5EDT,M3.2.0,M11.1.0` or `<+0330>-3:30`
+fn is_valid_timezone(tz: &str, zoneinfo: &Path) -> bool {
+    let tz = tz.strip_prefix(':').unwrap_or(tz);
+    if tz.is_empty() {
+        return false;
+    }
+
+    let path = Path::new(tz);
+    if path.is_absolute() {
+        return path.is_file();
+    }
+    let is_zone_name = path.components().all(|c| matches!(c, Component::Normal(_)));
+    if is_zone_name && zoneinfo.join(path).is_file() {
+        return true;
+    }
+
+    let name = r"(?:[A-Za-z]{3,}|<[A-Za-z0-9+\-]{3,}>)";
+    let offset = r"[+-]?\d{1,2}(?::\d{1,2}){0,2}";
+    let posix = format!(r"^{0}{1}(?:{0}(?:{1})?(?:,[^,]+,[^,]+)?)?$", name, offset);
+    Regex::new(&posix).is_ok_and(|re| re.is_match(tz))
+}
+
+pub fn parse_timezone(tz: &str) -> Result<String, String> {
+    let zoneinfo = std::env::var("TZDIR").unwrap_or_else(|_| String::from(ZONEINFO_DIR));
+    if is_valid_timezone(tz, Path::new(&zoneinfo)) {
+        Ok(tz.to_string())
+    } else {
+        Err(format!("unknown timezone '{}'", tz))
+    }
+}
+
+fn get_tm(secs: i64, utc: bool) -> libc::tm {
     let mut tm: libc::tm = unsafe { std::mem::zeroed() };
     let time_t = secs as libc::time_t;
 
     unsafe {
-        libc::localtime_r(&time_t, &mut tm);
+        if utc {
+            libc::gmtime_r(&time_t, &mut tm);
+        } else {
+            libc::localtime_r(&time_t, &mut tm);
+        }
     }
 
+    tm
+}
+
+// Half a Gregorian year, the cutoff `ls` uses for recent files
+const SIX_MONTHS: i64 = 31_556_952 / 2;
+
+fn format_long_iso(tm: &libc::tm) -> String {
     format!(
         "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
         tm.tm_year + 1900,
@@ -17,13 +84,138 @@ pub fn format_timestamp(secs: i64) -> String {
     )
 }
 
+// Like GNU `ls --time-style=iso`: month, day and time within the last six months, else the date
+fn format_iso(tm: &libc::tm, secs: i64, now: i64) -> String {
+    let recent = secs <= now && now - secs < SIX_MONTHS;
+    if recent {
+        format!(
+            "{:02}-{:02} {:02}:{:02}",
+            tm.tm_mon + 1,
+            tm.tm_mday,
+            tm.tm_hour,
+            tm.tm_min
+        )
+    } else {
+        format!(
+            "{:04}-{:02}-{:02}",
+            tm.tm_year + 1900,
+            tm.tm_mon + 1,
+            tm.tm_mday
+        )
+    }
+}
+
+fn format_rfc3339(tm: &libc::tm, nsec: i64) -> String {
+    let offset = tm.tm_gmtoff;
+    let sign = if offset < 0 { '-' } else { '+' };
+    let offset = offset.abs();
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:09}{}{:02}:{:02}",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday,
+        tm.tm_hour,
+        tm.tm_min,
+        tm.tm_sec,
+        nsec,
+        sign,
+        offset / 3600,
+        (offset % 3600) / 60
+    )
+}
+
+// Days run all the way up to a year, so a 12-day-old file reads `12d ago`
+const RELATIVE_UNITS: [(i64, &str); 4] = [
+    (365 * 86400, "y"),
+    (86400, "d"),
+    (3600, "h"),
+    (60, "m"),
+];
+
+pub fn format_relative(secs: i64, now: i64) -> String {
+    let diff = now - secs;
+    let abs = diff.abs();
+
+    let (count, unit) = RELATIVE_UNITS
+        .iter()
+        .find(|(size, _)| abs >= *size)
+        .map(|(size, unit)| (abs / size, *unit))
+        .unwrap_or((abs, "s"));
+
+    if diff < 0 {
+        format!("in {}{}", count, unit)
+    } else {
+        format!("{}{} ago", count, unit)
+    }
+}
+
+// strftime has no sub-second field, so `%N` is expanded to nanoseconds first
+fn format_strftime(tm: &libc::tm, nsec: i64, fmt: &str) -> String {
+    let mut expanded = String::with_capacity(fmt.len());
+    let mut chars = fmt.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            expanded.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('N') => expanded.push_str(&format!("{:09}", nsec)),
+            Some(next) => {
+                expanded.push('%');
+                expanded.push(next);
+            }
+            None => expanded.push('%'),
+        }
+    }
+
+    // TimeStyle rejects NUL bytes when parsing, so this only fails on a bad %N expansion
+    let c_fmt = match std::ffi::CString::new(expanded) {
+        Ok(f) => f,
+        Err(_) => return String::new(),
+    };
+
+    // strftime returns 0 when the buffer is too small, so grow it until the output fits.
+    // A format that legitimately expands to nothing stops at the cap.
+    let mut size = 256;
+    while size <= STRFTIME_MAX_LEN {
+        let mut buf = vec![0u8; size];
+        let len = unsafe {
+            libc::strftime(
+                buf.as_mut_ptr() as *mut libc::c_char,
+                buf.len(),
+                c_fmt.as_ptr(),
+                tm,
+            )
+        };
+        if len > 0 {
+            return String::from_utf8_lossy(&buf[..len]).into_owned();
+        }
+        size *= 2;
+    }
+
+    String::new()
+}
+
+pub fn format_time(secs: i64, nsec: i64, style: &TimeStyle, utc: bool, now: i64) -> String {
+    match style {
+        TimeStyle::LongIso => format_long_iso(&get_tm(secs, utc)),
+        TimeStyle::Iso => format_iso(&get_tm(secs, utc), secs, now),
+        TimeStyle::Rfc3339 => format_rfc3339(&get_tm(secs, utc), nsec),
+        TimeStyle::Relative => format_relative(secs, now),
+        TimeStyle::Epoch => secs.to_string(),
+        TimeStyle::Custom(fmt) => format_strftime(&get_tm(secs, utc), nsec, fmt),
+    }
+}
+
 pub fn format_size_with_commas(size: u64) -> String {
     let s = size.to_string();
     let chars: Vec<char> = s.chars().collect();
     let mut result = String::with_capacity(s.len() + s.len() / 3);
 
     for (i, c) in chars.iter().enumerate() {
-        if i > 0 && (chars.len() - i) % 3 == 0 {
+        if i > 0 && (chars.len() - i).is_multiple_of(3) {
             result.push(',');
         }
         result.push(*c);
@@ -32,6 +224,48 @@ pub fn format_size_with_commas(size: u64) -> String {
     result
 }
 
+const SIZE_UNITS_SHORT: [&str; 6] = ["K", "M", "G", "T", "P", "E"];
+const SIZE_UNITS_SI: [&str; 6] = ["kB", "MB", "GB", "TB", "PB", "EB"];
+const SIZE_UNITS_IEC: [&str; 6] = ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+
+// One decimal below 10 and whole numbers above, so `1.1G` and `15G` line up like `ls -h`
+fn format_size_scaled(size: u64, base: u64, units: &[&str], sep: &str, bytes: &str) -> String {
+    if size < base {
+        return format!("{}{}{}", size, sep, bytes).trim_end().to_string();
+    }
+
+    let mut value = size as f64 / base as f64;
+    let mut unit = 0;
+
+    while unit + 1 < units.len() && value >= base as f64 - 0.5 {
+        value /= base as f64;
+        unit += 1;
+    }
+
+    if value < 9.95 {
+        format!("{:.1}{}{}", value, sep, units[unit])
+    } else {
+        format!("{:.0}{}{}", value, sep, units[unit])
+    }
+}
+
+pub fn format_size_human(size: u64) -> String {
+    format_size_scaled(size, 1024, &SIZE_UNITS_SHORT, "", "")
+}
+
+pub fn format_size_si(size: u64) -> String {
+    format_size_scaled(size, 1000, &SIZE_UNITS_SI, " ", "B")
+}
+
+pub fn format_size_iec(size: u64) -> String {
+    format_size_scaled(size, 1024, &SIZE_UNITS_IEC, " ", "B")
+}
+
+// st_blocks counts 512-byte units; report 1K blocks like `ls -s`
+pub fn format_size_blocks(blocks: u64) -> String {
+    format_size_with_commas(blocks.div_ceil(2))
+}
+
 pub fn collapse_whitespace(s: &str) -> String {
     let mut result = String::with_capacity(s.len());
     let mut prev_was_space = true;
@@ -55,20 +289,98 @@ pub fn is_printable_ascii(b: u8) -> bool {
     (0x21..=0x7E).contains(&b) || (0x09..=0x0D).contains(&b)
 }
 
+// Splits a line into escape sequences and plain characters
+pub fn next_token(chars: &[char], i: usize) -> (usize, bool) {
+    if chars[i] != ESC || i + 1 >= chars.len() {
+        return (i + 1, false);
+    }
+
+    if chars[i + 1] != '[' {
+        return ((i + 2).min(chars.len()), true);
+    }
+
+    let mut j = i + 2;
+    while j < chars.len() && !('\x40'..='\x7e').contains(&chars[j]) {
+        j += 1;
+    }
+    ((j + 1).min(chars.len()), true)
+}
+
+pub fn strip_ansi(s: &str) -> String {
+    let chars: Vec<char> = s.chars().collect();
+    let mut result = String::with_capacity(s.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        let (next, is_escape) = next_token(&chars, i);
+        if !is_escape {
+            result.push(chars[i]);
+        }
+        i = next;
+    }
+
+    result
+}
+
+// Combining marks take no cell and East Asian wide characters and emoji take two
+pub fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x0300..=0x036f
+        | 0x1ab0..=0x1aff
+        | 0x1dc0..=0x1dff
+        | 0x200b..=0x200f
+        | 0x20d0..=0x20ff
+        | 0xfe00..=0xfe0f
+        | 0xfe20..=0xfe2f => 0,
+        0x1100..=0x115f
+        | 0x2e80..=0x303e
+        | 0x3041..=0x33ff
+        | 0x3400..=0x4dbf
+        | 0x4e00..=0x9fff
+        | 0xa000..=0xa4cf
+        | 0xac00..=0xd7a3
+        | 0xf900..=0xfaff
+        | 0xfe30..=0xfe4f
+        | 0xff00..=0xff60
+        | 0xffe0..=0xffe6
+        | 0x1f300..=0x1f64f
+        | 0x1f900..=0x1f9ff
+        | 0x20000..=0x2fffd
+        | 0x30000..=0x3fffd => 2,
+        _ => 1,
+    }
+}
+
+pub fn visible_width(s: &str) -> usize {
+    strip_ansi(s).chars().map(char_width).sum()
+}
+
+// Longest prefix of `s` that fits in `width` terminal cells
+pub fn take_width(s: &str, width: usize) -> String {
+    let mut used = 0;
+    s.chars()
+        .take_while(|&c| {
+            used += char_width(c);
+            used <= width
+        })
+        .collect()
+}
+
+// `max_len` counts terminal cells, so wide names are cut by how much room they take
 pub fn truncate_middle(s: &str, max_len: usize) -> String {
-    let char_count = s.chars().count();
-    if char_count <= max_len {
+    if visible_width(s) <= max_len {
         return s.to_string();
     }
 
     let ellipsis = "...";
     let ellipsis_len = ellipsis.len();
     let available = max_len - ellipsis_len;
-    let front_len = (available + 1) / 2;
+    let front_len = available.div_ceil(2);
     let back_len = available / 2;
 
-    let front: String = s.chars().take(front_len).collect();
-    let back: String = s.chars().skip(char_count - back_len).collect();
+    let front = take_width(s, front_len);
+    let reversed: String = s.chars().rev().collect();
+    let back: String = take_width(&reversed, back_len).chars().rev().collect();
 
     format!("{}{}{}", front, ellipsis, back)
 }
@@ -112,6 +424,47 @@ mod tests {
         assert_eq!(format_size_with_commas(1234567890123), "1,234,567,890,123");
     }
 
+    #[test]
+    fn test_format_size_human_small() {
+        assert_eq!(format_size_human(0), "0");
+        assert_eq!(format_size_human(1023), "1023");
+    }
+
+    #[test]
+    fn test_format_size_human_units() {
+        assert_eq!(format_size_human(1024), "1.0K");
+        assert_eq!(format_size_human(1536), "1.5K");
+        assert_eq!(format_size_human(15 * 1024 * 1024), "15M");
+        assert_eq!(format_size_human(1_181_116_006), "1.1G");
+    }
+
+    #[test]
+    fn test_format_size_human_rolls_over_unit() {
+        assert_eq!(format_size_human(1024 * 1024 - 1), "1.0M");
+    }
+
+    #[test]
+    fn test_format_size_si() {
+        assert_eq!(format_size_si(999), "999 B");
+        assert_eq!(format_size_si(1000), "1.0 kB");
+        assert_eq!(format_size_si(1_234_567_890), "1.2 GB");
+        assert_eq!(format_size_si(123_000_000), "123 MB");
+    }
+
+    #[test]
+    fn test_format_size_iec() {
+        assert_eq!(format_size_iec(512), "512 B");
+        assert_eq!(format_size_iec(1_234_567_890), "1.1 GiB");
+    }
+
+    #[test]
+    fn test_format_size_blocks() {
+        assert_eq!(format_size_blocks(0), "0");
+        assert_eq!(format_size_blocks(8), "4");
+        assert_eq!(format_size_blocks(3), "2");
+        assert_eq!(format_size_blocks(4096), "2,048");
+    }
+
     #[test]
     fn test_collapse_whitespace_empty() {
         assert_eq!(collapse_whitespace(""), "");
@@ -199,6 +552,32 @@ mod tests {
         assert!(is_printable_ascii(b'!'));
     }
 
+    #[test]
+    fn test_strip_ansi() {
+        assert_eq!(strip_ansi("\x1b[91mname\x1b[0m  x"), "name  x");
+        assert_eq!(visible_width("\x1b[1;38;5;208mab\x1b[0m"), 2);
+    }
+
+    #[test]
+    fn test_visible_width_wide_chars() {
+        assert_eq!(visible_width("日本語"), 6);
+        assert_eq!(visible_width("e\u{301}"), 1);
+    }
+
+    #[test]
+    fn test_take_width() {
+        assert_eq!(take_width("abcdef", 3), "abc");
+        assert_eq!(take_width("日本語", 5), "日本");
+        assert_eq!(take_width("ab", 5), "ab");
+    }
+
+    #[test]
+    fn test_truncate_middle_wide_chars() {
+        let result = truncate_middle("日本語のファイル名", 12);
+        assert_eq!(result, "日本...ル名");
+        assert!(visible_width(&result) <= 12);
+    }
+
     #[test]
     fn test_truncate_middle_no_truncation() {
         assert_eq!(truncate_middle("short", 10), "short");
@@ -221,8 +600,8 @@ mod tests {
     }
 
     #[test]
-    fn test_format_timestamp_format() {
-        let result = format_timestamp(1704067200);
+    fn test_format_time_long_iso_format() {
+        let result = format_time(1704067200, 0, &TimeStyle::LongIso, false, 0);
         let parts: Vec<&str> = result.split(' ').collect();
         assert_eq!(parts.len(), 2);
         assert_eq!(parts[0].len(), 10);
@@ -230,9 +609,95 @@ mod tests {
     }
 
     #[test]
-    fn test_format_timestamp_contains_dashes_colons() {
-        let result = format_timestamp(1704067200);
+    fn test_format_time_long_iso_contains_dashes_colons() {
+        let result = format_time(1704067200, 0, &TimeStyle::LongIso, false, 0);
         assert!(result.contains('-'));
         assert!(result.contains(':'));
     }
+
+    #[test]
+    fn test_format_time_long_iso_utc() {
+        let result = format_time(1704067200, 0, &TimeStyle::LongIso, true, 0);
+        assert_eq!(result, "2024-01-01 00:00:00");
+    }
+
+    #[test]
+    fn test_format_time_iso_utc() {
+        let style = TimeStyle::Iso;
+        let recent = format_time(1704067200, 0, &style, true, 1704067200 + 86400);
+        assert_eq!(recent, "01-01 00:00");
+        let old = format_time(1704067200, 0, &style, true, 1704067200 + 200 * 86400);
+        assert_eq!(old, "2024-01-01");
+        let future = format_time(1704067200, 0, &style, true, 1704067200 - 60);
+        assert_eq!(future, "2024-01-01");
+    }
+
+    #[test]
+    fn test_format_time_rfc3339_utc() {
+        let result = format_time(1704067200, 42, &TimeStyle::Rfc3339, true, 0);
+        assert_eq!(result, "2024-01-01T00:00:00.000000042+00:00");
+    }
+
+    #[test]
+    fn test_format_time_epoch() {
+        let result = format_time(1704067200, 0, &TimeStyle::Epoch, true, 0);
+        assert_eq!(result, "1704067200");
+    }
+
+    #[test]
+    fn test_format_time_custom() {
+        let style = TimeStyle::Custom(String::from("%Y/%m/%d %H:%M"));
+        let result = format_time(1704067200, 0, &style, true, 0);
+        assert_eq!(result, "2024/01/01 00:00");
+    }
+
+    #[test]
+    fn test_format_time_custom_nanos() {
+        let style = TimeStyle::Custom(String::from("%S.%N %%N"));
+        let result = format_time(1704067205, 123, &style, true, 0);
+        assert_eq!(result, "05.000000123 %N");
+    }
+
+    #[test]
+    fn test_format_time_custom_long_output() {
+        let style = TimeStyle::Custom("%Y-".repeat(200));
+        let result = format_time(1704067200, 0, &style, true, 0);
+        assert_eq!(result, "2024-".repeat(200));
+    }
+
+    #[test]
+    fn test_valid_timezone_from_zoneinfo() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("Europe")).unwrap();
+        std::fs::write(dir.path().join("Europe/Paris"), "TZif").unwrap();
+        assert!(is_valid_timezone("Europe/Paris", dir.path()));
+        assert!(is_valid_timezone(":Europe/Paris", dir.path()));
+        assert!(!is_valid_timezone("Europe/Atlantis", dir.path()));
+        assert!(!is_valid_timezone("Europe", dir.path()));
+        assert!(!is_valid_timezone("../Europe/Paris", dir.path()));
+    }
+
+    #[test]
+    fn test_valid_timezone_posix_strings() {
+        let none = Path::new("/nonexistent");
+        assert!(is_valid_timezone("UTC0", none));
+        assert!(is_valid_timezone("EST5EDT", none));
+        assert!(is_valid_timezone("EST5EDT,M3.2.0,M11.1.0", none));
+        assert!(is_valid_timezone("<+0330>-3:30", none));
+        assert!(!is_valid_timezone("", none));
+        assert!(!is_valid_timezone("Mars/Olympus", none));
+        assert!(!is_valid_timezone("EST", none));
+    }
+
+    #[test]
+    fn test_format_relative() {
+        assert_eq!(format_relative(1000, 1000), "0s ago");
+        assert_eq!(format_relative(1000, 1045), "45s ago");
+        assert_eq!(format_relative(1000, 1000 + 180), "3m ago");
+        assert_eq!(format_relative(0, 2 * 86400), "2d ago");
+        assert_eq!(format_relative(0, 12 * 86400), "12d ago");
+        assert_eq!(format_relative(0, 364 * 86400), "364d ago");
+        assert_eq!(format_relative(0, 400 * 86400), "1y ago");
+        assert_eq!(format_relative(7200, 0), "in 2h");
+    }
 }